@@ -0,0 +1,570 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+#[cfg(unix)]
+use crate::control::ControlServer;
+use crate::{
+    font::{Font, STANDARD_FONT},
+    hooks::Hooks,
+    keymap::{Action, Keymap},
+    models::{
+        cycle::{Period, Profile},
+        history::History,
+        pomodoro_mode::PomodoroMode,
+        saved_state::StateFile,
+        study_mode::StudyMode,
+        timer::Timer,
+    },
+    notifications::NotificationsConfig,
+    paths::{expand_tilde, xdg_dir},
+    theme::{Theme, DARK_THEME},
+    Args,
+};
+
+pub const DEFAULT_TASK_FILE: &str = "tasks";
+pub const DEFAULT_PROFILE: &str = "default";
+pub const FLOWTIME_PROFILE: &str = "flowtime";
+/// Most pomodoros before a long break, as the default profile holds two periods for each.
+pub const MAX_LONG_BREAK_INTERVAL: u64 = 100;
+/// Longest period or break in minutes, a day.
+pub const MAX_DURATION: u64 = 24 * 60;
+
+/// Settings loaded from `config.toml`, with command-line flags applied on top.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    pub timer: TimerConfig,
+    pub tasks: TasksConfig,
+    pub history: HistoryConfig,
+    pub state: StateConfig,
+    pub ui: UiConfig,
+    pub control: ControlConfig,
+    pub hooks: Hooks,
+    pub notifications: NotificationsConfig,
+    pub flowtime: FlowtimeConfig,
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    pub keys: BTreeMap<Action, Vec<String>>,
+    /// The theme named by `ui.theme`, loaded along with the config.
+    #[serde(skip)]
+    pub theme: Theme,
+    /// The font named by `ui.font`, loaded along with the config.
+    #[serde(skip)]
+    pub font: Font,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct TimerConfig {
+    /// Pomodoro timer length in minutes.
+    pub pomodoro: u64,
+    /// Short break timer length in minutes.
+    pub short_break: u64,
+    /// Long break timer length in minutes.
+    pub long_break: u64,
+    /// Number of pomodoros before a long break.
+    pub long_break_interval: u64,
+    /// Name of the profile to start with.
+    pub profile: String,
+    /// Keep counting past zero instead of moving on to the next timer.
+    pub overtime: bool,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            pomodoro: 25,
+            short_break: 5,
+            long_break: 15,
+            long_break_interval: 4,
+            profile: String::from(DEFAULT_PROFILE),
+            overtime: false,
+        }
+    }
+}
+
+/// A sequence of periods configured under `[profiles.<name>]`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    pub periods: Vec<Period>,
+}
+
+/// How breaks are earned in flowtime, configured under `[flowtime]`.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct FlowtimeConfig {
+    /// A break lasts the time spent focusing divided by this.
+    pub break_ratio: u64,
+    /// Shortest break in minutes.
+    pub min_break: u64,
+    /// Longest break in minutes.
+    pub max_break: u64,
+}
+
+impl Default for FlowtimeConfig {
+    fn default() -> Self {
+        Self {
+            break_ratio: 5,
+            min_break: 1,
+            max_break: 30,
+        }
+    }
+}
+
+impl FlowtimeConfig {
+    /// The break earned by focusing for `focus_time`.
+    pub fn break_for(&self, focus_time: Duration) -> Duration {
+        (focus_time / self.break_ratio as u32).clamp(
+            Duration::from_secs(self.min_break * 60),
+            Duration::from_secs(self.max_break * 60),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct TasksConfig {
+    /// Path to the tasks file. When unset, a `tasks` file in the current directory is used if
+    /// there is one.
+    pub file: Option<PathBuf>,
+    /// When changes to the task list are saved without pressing `S`.
+    pub autosave: Autosave,
+    /// Seconds between saves when autosaving on an interval.
+    pub autosave_interval: u64,
+}
+
+impl Default for TasksConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            autosave: Autosave::Off,
+            autosave_interval: 60,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Autosave {
+    Off,
+    /// Save after every change.
+    OnChange,
+    /// Save unsaved changes every `autosave_interval` seconds.
+    Interval,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct HistoryConfig {
+    /// Path to the session history file.
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct StateConfig {
+    /// Path to the file the running timer is saved to.
+    pub file: Option<PathBuf>,
+    /// How often the running timer is saved, in seconds.
+    pub save_interval: u64,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            save_interval: 30,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct UiConfig {
+    /// The view the application opens in.
+    pub start_mode: StudyMode,
+    /// How often the screen is redrawn, in milliseconds.
+    pub tick_rate: u64,
+    /// `dark`, `light`, the name of a theme in the themes directory or the path to a theme file.
+    pub theme: String,
+    /// `standard`, `small`, `block` or the path to a FIGlet `.flf` font to draw the timer in.
+    pub font: String,
+    /// Capture the mouse to click on the timer, tasks and modes. Off by default, as it stops
+    /// the terminal from selecting text.
+    pub mouse: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            start_mode: StudyMode::Normal,
+            tick_rate: 250,
+            theme: String::from(DARK_THEME),
+            font: String::from(STANDARD_FONT),
+            mouse: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ControlConfig {
+    /// Whether to listen on the control socket.
+    pub enabled: bool,
+    /// Path to the control socket.
+    pub socket: Option<PathBuf>,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            socket: None,
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/pomodoro/config.toml`, falling back to `~/.config/pomodoro/config.toml`.
+    pub fn default_path() -> PathBuf {
+        xdg_dir("XDG_CONFIG_HOME", ".config")
+            .join("pomodoro")
+            .join("config.toml")
+    }
+
+    /// Load the config file given on the command line, or the default one if it exists, then
+    /// apply any command-line overrides.
+    pub fn load(args: &Args) -> Result<Self, ConfigError> {
+        let mut config = match &args.config {
+            Some(path) => Config::from_file(&expand_tilde(Path::new(path)))?,
+            None => match Config::from_file(&Config::default_path()) {
+                Err(ConfigError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {
+                    Config::default()
+                }
+                config => config?,
+            },
+        };
+
+        config.apply_args(args);
+        config.validate()?;
+        config.theme = Theme::load(&config.ui.theme)?;
+        config.font = Font::load(&config.ui.font)?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        toml::from_str(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn apply_args(&mut self, args: &Args) {
+        if let Some(pomodoro_time) = args.pomodoro_time {
+            self.timer.pomodoro = pomodoro_time;
+        }
+        if let Some(short_break_time) = args.short_break_time {
+            self.timer.short_break = short_break_time;
+        }
+        if let Some(long_break_time) = args.long_break_time {
+            self.timer.long_break = long_break_time;
+        }
+        if let Some(long_break_interval) = args.long_break_interval {
+            self.timer.long_break_interval = long_break_interval;
+        }
+        if let Some(profile) = &args.profile {
+            self.timer.profile = profile.clone();
+        }
+        if args.overtime {
+            self.timer.overtime = true;
+        }
+        if args.mouse {
+            self.ui.mouse = true;
+        }
+        if let Some(task_file_path) = &args.task_file_path {
+            self.tasks.file = Some(PathBuf::from(task_file_path));
+        }
+        if let Some(history_file_path) = &args.history_file_path {
+            self.history.file = Some(PathBuf::from(history_file_path));
+        }
+        if let Some(font) = &args.font {
+            self.ui.font = font.clone();
+        }
+        if let Some(theme) = &args.theme {
+            self.ui.theme = theme.clone();
+        }
+        if let Some(focus_mode) = &args.focus_mode {
+            self.ui.start_mode = match focus_mode.to_lowercase().as_str() {
+                "true" => StudyMode::Zen,
+                _ => StudyMode::Normal,
+            };
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(1..=MAX_LONG_BREAK_INTERVAL).contains(&self.timer.long_break_interval) {
+            return Err(ConfigError::Invalid(format!(
+                "`timer.long_break_interval` must be between 1 and {}",
+                MAX_LONG_BREAK_INTERVAL
+            )));
+        }
+
+        for (key, minutes) in [
+            ("timer.pomodoro", self.timer.pomodoro),
+            ("timer.short_break", self.timer.short_break),
+            ("timer.long_break", self.timer.long_break),
+            ("flowtime.min_break", self.flowtime.min_break),
+            ("flowtime.max_break", self.flowtime.max_break),
+        ] {
+            // A zero length timer would finish as soon as it started, over and over
+            if !(1..=MAX_DURATION).contains(&minutes) {
+                return Err(ConfigError::Invalid(format!(
+                    "`{}` must be between 1 and {} minutes",
+                    key, MAX_DURATION
+                )));
+            }
+        }
+
+        for (name, profile) in &self.profiles {
+            if profile.periods.is_empty() {
+                return Err(ConfigError::Invalid(format!(
+                    "profile `{}` has no periods",
+                    name
+                )));
+            }
+
+            let previous_modes = profile
+                .periods
+                .iter()
+                .cycle()
+                .skip(profile.periods.len() - 1);
+            for (index, (period, previous)) in
+                profile.periods.iter().zip(previous_modes).enumerate()
+            {
+                // Flowtime, and breaks following it, earn their own length
+                let earns_length = period.mode == PomodoroMode::Flowtime
+                    || (!period.mode.is_focus() && previous.mode == PomodoroMode::Flowtime);
+
+                if period.duration == 0 && !earns_length {
+                    return Err(ConfigError::Invalid(format!(
+                        "period {} of profile `{}` needs a `duration`",
+                        index + 1,
+                        name
+                    )));
+                }
+                if period.duration > MAX_DURATION {
+                    return Err(ConfigError::Invalid(format!(
+                        "period {} of profile `{}` can't be longer than {} minutes",
+                        index + 1,
+                        name,
+                        MAX_DURATION
+                    )));
+                }
+            }
+        }
+
+        if !self
+            .profiles()
+            .iter()
+            .any(|profile| profile.name == self.timer.profile)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "unknown profile `{}`",
+                self.timer.profile
+            )));
+        }
+
+        if !(1..=u32::MAX as u64).contains(&self.flowtime.break_ratio) {
+            return Err(ConfigError::Invalid(format!(
+                "`flowtime.break_ratio` must be between 1 and {}",
+                u32::MAX
+            )));
+        }
+
+        if self.flowtime.min_break > self.flowtime.max_break {
+            return Err(ConfigError::Invalid(String::from(
+                "`flowtime.min_break` can't be longer than `flowtime.max_break`",
+            )));
+        }
+
+        if let Err(err) = Keymap::new(&self.keys) {
+            return Err(ConfigError::Invalid(format!("[keys]: {}", err)));
+        }
+
+        if self.tasks.autosave_interval == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "`tasks.autosave_interval` must be at least 1",
+            )));
+        }
+
+        if self.state.save_interval == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "`state.save_interval` must be at least 1",
+            )));
+        }
+
+        if self.ui.tick_rate == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "`ui.tick_rate` must be at least 1",
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Create a new timer for the given mode using the configured lengths.
+    pub fn timer_for(&self, mode: PomodoroMode) -> Timer {
+        let minutes = match mode {
+            PomodoroMode::Pomodoro => self.timer.pomodoro,
+            PomodoroMode::ShortBreak => self.timer.short_break,
+            PomodoroMode::LongBreak => self.timer.long_break,
+            PomodoroMode::Flowtime => return Timer::count_up(mode),
+        };
+
+        Timer::new(Duration::from_secs(minutes * 60), mode)
+    }
+
+    /// Every profile, starting with the built-in ones unless they've been overridden. The
+    /// `default` profile follows the lengths and long break interval under `[timer]`.
+    pub fn profiles(&self) -> Vec<Profile> {
+        let mut default_periods = vec![];
+        for pomodoro in 1..=self.timer.long_break_interval {
+            default_periods.push(Period::new(PomodoroMode::Pomodoro, self.timer.pomodoro));
+
+            if pomodoro == self.timer.long_break_interval {
+                default_periods.push(Period::new(PomodoroMode::LongBreak, self.timer.long_break));
+            } else {
+                default_periods.push(Period::new(
+                    PomodoroMode::ShortBreak,
+                    self.timer.short_break,
+                ));
+            }
+        }
+
+        let built_in = [
+            (DEFAULT_PROFILE, default_periods),
+            (
+                FLOWTIME_PROFILE,
+                vec![
+                    Period::new(PomodoroMode::Flowtime, 0),
+                    Period::new(PomodoroMode::ShortBreak, 0),
+                ],
+            ),
+        ];
+
+        built_in
+            .into_iter()
+            .filter(|(name, _)| !self.profiles.contains_key(*name))
+            .map(|(name, periods)| Profile {
+                name: name.to_string(),
+                periods,
+            })
+            .chain(self.profiles.iter().map(|(name, profile)| Profile {
+                name: name.clone(),
+                periods: profile.periods.clone(),
+            }))
+            .collect()
+    }
+
+    /// The profile named by `timer.profile`, falling back to the first one.
+    pub fn active_profile(&self) -> Profile {
+        let mut profiles = self.profiles();
+        let index = profiles
+            .iter()
+            .position(|profile| profile.name == self.timer.profile)
+            .unwrap_or(0);
+
+        profiles.swap_remove(index)
+    }
+
+    pub fn task_file(&self) -> PathBuf {
+        expand_tilde(
+            self.tasks
+                .file
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_TASK_FILE)),
+        )
+    }
+
+    pub fn autosave_interval(&self) -> Duration {
+        Duration::from_secs(self.tasks.autosave_interval)
+    }
+
+    pub fn history_file(&self) -> PathBuf {
+        match &self.history.file {
+            Some(path) => expand_tilde(path),
+            None => History::default_path(),
+        }
+    }
+
+    pub fn state_file(&self) -> PathBuf {
+        match &self.state.file {
+            Some(path) => expand_tilde(path),
+            None => StateFile::default_path(),
+        }
+    }
+
+    pub fn save_interval(&self) -> Duration {
+        Duration::from_secs(self.state.save_interval)
+    }
+
+    #[cfg(unix)]
+    pub fn control_socket(&self) -> PathBuf {
+        match &self.control.socket {
+            Some(path) => expand_tilde(path),
+            None => ControlServer::default_path(),
+        }
+    }
+
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.ui.tick_rate)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ConfigError::Parse { path, source } => write!(f, "{}: {}", path.display(), source),
+            ConfigError::Invalid(reason) => write!(f, "invalid config: {}", reason),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::Invalid(_) => None,
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{pomodoro_mode::PomodoroMode, timer::TimerStatus};
+
+/// Exit code used by `pomodoro status` when no instance is running.
+pub const EXIT_NOT_RUNNING: i32 = 3;
+
+/// Exit code used by `pomodoro status` when the instance doesn't answer in time.
+pub const EXIT_TIMED_OUT: i32 = 4;
+
+/// How long the client waits on a running instance, which may be stuck in a hook.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Commands accepted over the control socket, one per connection.
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    Toggle,
+    Reset,
+    Mode(PomodoroMode),
+    /// Select the task at the given index, counting from 1 as shown in the task list.
+    Select(usize),
+    SelectNext,
+    SelectPrevious,
+    /// Mark or unmark a task as complete, defaulting to the selected task.
+    Complete(Option<usize>),
+    /// Credit finished pomodoros to a task, defaulting to the selected task.
+    Activate(Option<usize>),
+    /// Reply with the current `Status` as JSON.
+    Status,
+}
+
+/// A snapshot of the running timer, sent in reply to the `status` command.
+#[derive(Serialize, Deserialize)]
+pub struct Status {
+    pub mode: PomodoroMode,
+    pub status: TimerStatus,
+    /// Time remaining in seconds.
+    pub time_remaining: u64,
+    /// Seconds spent past the end of the timer when counting overtime.
+    #[serde(default)]
+    pub overtime: u64,
+    pub mm_ss: String,
+    pub hh_mm_ss: String,
+    pub percentage: u16,
+    /// Title of the active task.
+    pub task: Option<String>,
+}
+
+impl Status {
+    /// Fill in a format string such as `"{mode} {mm_ss}"`. Unknown placeholders are left as is.
+    pub fn format(&self, format: &str) -> String {
+        format
+            .replace("{mode}", &self.mode.to_string())
+            .replace(
+                "{status}",
+                match self.status {
+                    TimerStatus::Playing => "playing",
+                    TimerStatus::Paused => "paused",
+                },
+            )
+            .replace("{time_remaining}", &self.time_remaining.to_string())
+            .replace("{overtime}", &self.overtime.to_string())
+            .replace("{mm_ss}", &self.mm_ss)
+            .replace("{hh_mm_ss}", &self.hh_mm_ss)
+            .replace("{percentage}", &self.percentage.to_string())
+            .replace("{task}", self.task.as_deref().unwrap_or_default())
+    }
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(command: &str) -> Result<Self, Self::Err> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let argument = words.next();
+
+        if words.next().is_some() {
+            return Err(format!("too many arguments for `{}`", name));
+        }
+
+        let parse_index = |argument: &str| match argument.parse::<usize>() {
+            Ok(index) if index > 0 => Ok(index - 1),
+            _ => Err(format!("invalid task number `{}`", argument)),
+        };
+
+        match (name, argument) {
+            ("pause", None) => Ok(ControlCommand::Pause),
+            ("resume", None) => Ok(ControlCommand::Resume),
+            ("toggle", None) => Ok(ControlCommand::Toggle),
+            ("reset", None) => Ok(ControlCommand::Reset),
+            ("status", None) => Ok(ControlCommand::Status),
+            ("mode", Some(mode)) => match mode {
+                "pomodoro" => Ok(ControlCommand::Mode(PomodoroMode::Pomodoro)),
+                "short" | "short_break" => Ok(ControlCommand::Mode(PomodoroMode::ShortBreak)),
+                "long" | "long_break" => Ok(ControlCommand::Mode(PomodoroMode::LongBreak)),
+                "flowtime" => Ok(ControlCommand::Mode(PomodoroMode::Flowtime)),
+                _ => Err(format!("unknown mode `{}`", mode)),
+            },
+            ("select", Some("next")) => Ok(ControlCommand::SelectNext),
+            ("select", Some("previous")) => Ok(ControlCommand::SelectPrevious),
+            ("select", Some(index)) => parse_index(index).map(ControlCommand::Select),
+            ("complete", None) => Ok(ControlCommand::Complete(None)),
+            ("complete", Some(index)) => {
+                parse_index(index).map(|index| ControlCommand::Complete(Some(index)))
+            }
+            ("activate", None) => Ok(ControlCommand::Activate(None)),
+            ("activate", Some(index)) => {
+                parse_index(index).map(|index| ControlCommand::Activate(Some(index)))
+            }
+            ("", _) => Err(String::from("empty command")),
+            _ => Err(format!("invalid command `{}`", command.trim())),
+        }
+    }
+}
+
+/// Listens on a Unix socket so a running timer can be driven from scripts, e.g.
+/// `echo pause | nc -U $XDG_RUNTIME_DIR/pomodoro.sock`.
+pub struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl ControlServer {
+    /// `$XDG_RUNTIME_DIR/pomodoro.sock`, falling back to a per-user socket in the temp directory.
+    pub fn default_path() -> PathBuf {
+        match env::var_os("XDG_RUNTIME_DIR").filter(|path| !path.is_empty()) {
+            Some(runtime_dir) => Path::new(&runtime_dir).join("pomodoro.sock"),
+            None => env::temp_dir().join(format!(
+                "pomodoro-{}.sock",
+                env::var("USER").unwrap_or_default()
+            )),
+        }
+    }
+
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        if path.exists() {
+            // Only clean up the socket if nothing is listening on it anymore
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    ErrorKind::AddrInUse,
+                    format!("another instance is listening on {}", path.display()),
+                ));
+            }
+
+            fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Handle any pending connections without blocking. Each connection sends a single command
+    /// line and receives `handle`'s response, or `error: <reason>`, back.
+    pub fn poll(&self, mut handle: impl FnMut(&str) -> Result<String, String>) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    // A misbehaving client shouldn't take the timer down with it
+                    let _ = respond(stream, &mut handle);
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+fn respond(
+    mut stream: UnixStream,
+    handle: &mut impl FnMut(&str) -> Result<String, String>,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_millis(100)))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let response = match handle(line.trim()) {
+        Ok(response) => response,
+        Err(reason) => format!("error: {}", reason),
+    };
+
+    writeln!(stream, "{}", response)
+}
+
+/// Send a single command to a running instance and return its response.
+pub fn send(path: &Path, command: &str) -> io::Result<Result<String, String>> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    writeln!(stream, "{}", command)?;
+
+    let mut response = String::new();
+    BufReader::new(&stream).read_line(&mut response)?;
+    let response = response.trim_end().to_string();
+
+    Ok(match response.strip_prefix("error: ") {
+        Some(reason) => Err(reason.to_string()),
+        None => Ok(response),
+    })
+}
+
+/// Print the status of the instance listening on `path`, as JSON or through `format`.
+/// Returns the process exit code.
+pub fn print_status(path: &Path, format: Option<&str>) -> i32 {
+    let response = match send(path, "status") {
+        Ok(response) => response,
+        Err(err)
+            if matches!(
+                err.kind(),
+                ErrorKind::NotFound | ErrorKind::ConnectionRefused
+            ) =>
+        {
+            eprintln!("pomodoro is not running");
+            return EXIT_NOT_RUNNING;
+        }
+        Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+            eprintln!("pomodoro is not responding");
+            return EXIT_TIMED_OUT;
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            return 1;
+        }
+    };
+
+    let status = response.and_then(|response| {
+        serde_json::from_str::<Status>(&response).map_err(|err| err.to_string())
+    });
+
+    match status {
+        Ok(status) => {
+            match format {
+                Some(format) => println!("{}", status.format(format)),
+                None => println!("{}", serde_json::to_string(&status).unwrap_or_default()),
+            }
+
+            0
+        }
+        Err(reason) => {
+            eprintln!("{}", reason);
+            1
+        }
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
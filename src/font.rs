@@ -0,0 +1,126 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use figlet_rs::FIGfont;
+
+use crate::{config::ConfigError, paths::expand_tilde};
+
+pub const STANDARD_FONT: &str = "standard";
+pub const SMALL_FONT: &str = "small";
+pub const BLOCK_FONT: &str = "block";
+
+const SMALL_FONT_CONTENT: &str = include_str!("fonts/small.flf");
+const BLOCK_FONT_CONTENT: &str = include_str!("fonts/block.flf");
+
+/// Every font file has a glyph for the 95 printable ASCII characters and 7 German ones.
+const REQUIRED_CHARACTERS: usize = 102;
+
+/// A FIGlet font used to draw the timer, parsed once and shared.
+#[derive(Clone)]
+pub struct Font {
+    pub name: String,
+    figfont: Rc<FIGfont>,
+}
+
+impl Font {
+    fn new(name: &str, figfont: FIGfont) -> Self {
+        Self {
+            name: name.to_string(),
+            figfont: Rc::new(figfont),
+        }
+    }
+
+    fn bundled(name: &str) -> Option<Self> {
+        let figfont = match name {
+            STANDARD_FONT => FIGfont::standard(),
+            SMALL_FONT => parse(SMALL_FONT_CONTENT),
+            BLOCK_FONT => parse(BLOCK_FONT_CONTENT),
+            _ => return None,
+        };
+
+        Some(Self::new(
+            name,
+            figfont.expect("bundled fonts are valid FIGlet fonts"),
+        ))
+    }
+
+    /// The narrowest bundled font, used when the timer doesn't fit in the chosen one.
+    pub fn small() -> Self {
+        Self::bundled(SMALL_FONT).unwrap()
+    }
+
+    /// Whether `name` is a path to a font file rather than one of the bundled fonts.
+    fn is_path(name: &str) -> bool {
+        name.contains('/') || name.ends_with(".flf")
+    }
+
+    pub fn path(name: &str) -> PathBuf {
+        expand_tilde(Path::new(name))
+    }
+
+    /// Load a bundled font by name, or any `.flf` font file by path.
+    pub fn load(name: &str) -> Result<Self, ConfigError> {
+        if let Some(font) = Self::bundled(name) {
+            return Ok(font);
+        }
+
+        if !Self::is_path(name) {
+            return Err(ConfigError::Invalid(format!(
+                "unknown font `{}`, expected `{}`, `{}`, `{}` or the path to a .flf file",
+                name, STANDARD_FONT, SMALL_FONT, BLOCK_FONT
+            )));
+        }
+
+        let path = Self::path(name);
+        let content = fs::read_to_string(&path).map_err(|source| ConfigError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let figfont = parse(&content)
+            .map_err(|err| ConfigError::Invalid(format!("{}: {}", path.display(), err)))?;
+
+        Ok(Self::new(name, figfont))
+    }
+
+    /// Draw `text` in the font, `None` if the font has none of its characters.
+    pub fn render(&self, text: &str) -> Option<String> {
+        self.figfont.convert(text).map(|figure| figure.to_string())
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::bundled(STANDARD_FONT).unwrap()
+    }
+}
+
+/// The character height and number of comment lines from a font's header line, e.g.
+/// `flf2a$ 6 5 16 15 11`. Read here as figlet-rs doesn't expose its header the same way in
+/// every release.
+fn header(line: &str) -> Option<(usize, usize)> {
+    let mut fields = line.split_whitespace();
+    if !fields.next()?.starts_with("flf2a") {
+        return None;
+    }
+
+    // Height, baseline, max length, old layout, then the comment lines
+    let fields: Vec<&str> = fields.collect();
+    Some((fields.first()?.parse().ok()?, fields.get(4)?.parse().ok()?))
+}
+
+/// Parse the contents of a font file. Files missing some of the required characters are turned
+/// away up front, as the parser can't cope with them.
+fn parse(content: &str) -> Result<FIGfont, String> {
+    let (height, comment_lines) = header(content.lines().next().unwrap_or_default())
+        .ok_or_else(|| String::from("not a FIGlet font"))?;
+    let required_lines = 1 + comment_lines + REQUIRED_CHARACTERS * height;
+
+    if height < 1 || content.lines().count() < required_lines {
+        return Err(String::from("not a complete FIGlet font"));
+    }
+
+    FIGfont::from_content(content)
+}
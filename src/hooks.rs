@@ -0,0 +1,108 @@
+use std::{
+    io,
+    process::{Command, Stdio},
+    thread,
+};
+
+use serde::Deserialize;
+
+use crate::models::{pomodoro_mode::PomodoroMode, timer::Timer};
+
+#[derive(Clone, Copy)]
+pub enum HookEvent {
+    PomodoroStart,
+    PomodoroEnd,
+    BreakStart,
+    BreakEnd,
+    Pause,
+    Resume,
+    TaskCompleted,
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::PomodoroStart => "pomodoro_start",
+            HookEvent::PomodoroEnd => "pomodoro_end",
+            HookEvent::BreakStart => "break_start",
+            HookEvent::BreakEnd => "break_end",
+            HookEvent::Pause => "pause",
+            HookEvent::Resume => "resume",
+            HookEvent::TaskCompleted => "task_completed",
+        }
+    }
+
+    /// The start or end event for a timer in the given mode. Flowtime counts as a pomodoro.
+    pub fn start_of(mode: PomodoroMode) -> Self {
+        if mode.is_focus() {
+            HookEvent::PomodoroStart
+        } else {
+            HookEvent::BreakStart
+        }
+    }
+
+    pub fn end_of(mode: PomodoroMode) -> Self {
+        if mode.is_focus() {
+            HookEvent::PomodoroEnd
+        } else {
+            HookEvent::BreakEnd
+        }
+    }
+}
+
+/// Shell commands run when the timer changes state, configured under `[hooks]`.
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Hooks {
+    pub pomodoro_start: Option<String>,
+    pub pomodoro_end: Option<String>,
+    pub break_start: Option<String>,
+    pub break_end: Option<String>,
+    pub pause: Option<String>,
+    pub resume: Option<String>,
+    pub task_completed: Option<String>,
+}
+
+impl Hooks {
+    fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::PomodoroStart => self.pomodoro_start.as_deref(),
+            HookEvent::PomodoroEnd => self.pomodoro_end.as_deref(),
+            HookEvent::BreakStart => self.break_start.as_deref(),
+            HookEvent::BreakEnd => self.break_end.as_deref(),
+            HookEvent::Pause => self.pause.as_deref(),
+            HookEvent::Resume => self.resume.as_deref(),
+            HookEvent::TaskCompleted => self.task_completed.as_deref(),
+        }
+    }
+
+    /// Run the command configured for `event`, if any, in the background. The command is
+    /// passed to `sh -c` with the event described in `POMODORO_*` environment variables.
+    pub fn run(&self, event: HookEvent, timer: &Timer, task: Option<&str>) -> io::Result<()> {
+        let command = match self.command(event) {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("POMODORO_EVENT", event.name())
+            .env("POMODORO_MODE", timer.pomodoro_mode.name())
+            .env(
+                "POMODORO_DURATION",
+                timer.total_time.unwrap_or_default().as_secs().to_string(),
+            )
+            .env("POMODORO_ELAPSED", timer.elapsed().as_secs().to_string())
+            .env("POMODORO_TASK", task.unwrap_or_default())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        // Reap the hook once it exits without holding up the UI
+        thread::spawn(move || child.wait());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,562 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Everything that can be bound to a key in the main view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Pomodoro,
+    ShortBreak,
+    LongBreak,
+    Flowtime,
+    NextTimer,
+    PickProfile,
+    ToggleTimer,
+    ResetTimer,
+    ToggleZen,
+    ToggleStats,
+    GroupStats,
+    NextTask,
+    PreviousTask,
+    CollapseTask,
+    ExpandTask,
+    FilterTasks,
+    AddTask,
+    AddSubtask,
+    EditTask,
+    DeleteTask,
+    ActivateTask,
+    SaveTasks,
+    ToggleTaskCompleted,
+    AddPomodoro,
+    RemovePomodoro,
+    ToggleHelp,
+    Quit,
+}
+
+impl fmt::Display for Action {
+    /// The action's name in the `[keys]` config table, e.g. `toggle_timer`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut name = String::new();
+        for c in format!("{:?}", self).chars() {
+            if c.is_uppercase() && !name.is_empty() {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Groups of actions shown together in the help popup.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Section {
+    Timer,
+    Tasks,
+    Misc,
+}
+
+impl Section {
+    pub const ALL: [Section; 3] = [Section::Timer, Section::Tasks, Section::Misc];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Section::Timer => "Timer:",
+            Section::Tasks => "Tasks:",
+            Section::Misc => "Misc:",
+        }
+    }
+}
+
+impl Action {
+    /// Every action, in the order they're listed in the help popup.
+    pub const ALL: [Action; 27] = [
+        Action::Pomodoro,
+        Action::ShortBreak,
+        Action::LongBreak,
+        Action::Flowtime,
+        Action::NextTimer,
+        Action::PickProfile,
+        Action::ToggleTimer,
+        Action::ResetTimer,
+        Action::ToggleZen,
+        Action::ToggleStats,
+        Action::GroupStats,
+        Action::NextTask,
+        Action::PreviousTask,
+        Action::CollapseTask,
+        Action::ExpandTask,
+        Action::FilterTasks,
+        Action::AddTask,
+        Action::AddSubtask,
+        Action::EditTask,
+        Action::DeleteTask,
+        Action::ActivateTask,
+        Action::SaveTasks,
+        Action::ToggleTaskCompleted,
+        Action::AddPomodoro,
+        Action::RemovePomodoro,
+        Action::ToggleHelp,
+        Action::Quit,
+    ];
+
+    pub fn section(&self) -> Section {
+        match self {
+            Action::Pomodoro
+            | Action::ShortBreak
+            | Action::LongBreak
+            | Action::Flowtime
+            | Action::NextTimer
+            | Action::PickProfile
+            | Action::ToggleTimer
+            | Action::ResetTimer
+            | Action::ToggleZen
+            | Action::ToggleStats
+            | Action::GroupStats => Section::Timer,
+            Action::NextTask
+            | Action::PreviousTask
+            | Action::CollapseTask
+            | Action::ExpandTask
+            | Action::FilterTasks
+            | Action::AddTask
+            | Action::AddSubtask
+            | Action::EditTask
+            | Action::DeleteTask
+            | Action::ActivateTask
+            | Action::SaveTasks
+            | Action::ToggleTaskCompleted
+            | Action::AddPomodoro
+            | Action::RemovePomodoro => Section::Tasks,
+            Action::ToggleHelp | Action::Quit => Section::Misc,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Pomodoro => "Pomodoro timer",
+            Action::ShortBreak => "Short break timer",
+            Action::LongBreak => "Long break timer",
+            Action::Flowtime => "Flowtime timer",
+            Action::NextTimer => "Start next timer when in overtime or flowtime",
+            Action::PickProfile => "Switch profile",
+            Action::ToggleTimer => "Pause/Unpause timer",
+            Action::ResetTimer => "Reset timer",
+            Action::ToggleZen => "Toggle focus mode",
+            Action::ToggleStats => "Toggle statistics view",
+            Action::GroupStats => "Group statistics by task, project or tag",
+            Action::NextTask => "Select next task",
+            Action::PreviousTask => "Select previous task",
+            Action::CollapseTask => "Collapse task, or select its parent",
+            Action::ExpandTask => "Expand task, or select its first subtask",
+            Action::FilterTasks => "Filter tasks by title, #tag, @project or is:done",
+            Action::AddTask => "Add task",
+            Action::AddSubtask => "Add subtask to selected task",
+            Action::EditTask => "Edit selected task",
+            Action::DeleteTask => "Delete selected task",
+            Action::ActivateTask => "Credit pomodoros to selected task",
+            Action::SaveTasks => "Save tasks",
+            Action::ToggleTaskCompleted => "Mark/Unmark task as complete",
+            Action::AddPomodoro => "Add pomodoro to selected task",
+            Action::RemovePomodoro => "Remove pomodoro from selected task",
+            Action::ToggleHelp => "Toggle this help menu",
+            Action::Quit => "Quit application",
+        }
+    }
+
+    /// The keys bound to the action when the config file doesn't say otherwise.
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Pomodoro => &["p"],
+            Action::ShortBreak => &["s"],
+            Action::LongBreak => &["l"],
+            Action::Flowtime => &["w"],
+            Action::NextTimer => &["n"],
+            Action::PickProfile => &["P"],
+            Action::ToggleTimer => &["space"],
+            Action::ResetTimer => &["r"],
+            Action::ToggleZen => &["f"],
+            Action::ToggleStats => &["v"],
+            Action::GroupStats => &["G"],
+            Action::NextTask => &["j", "down"],
+            Action::PreviousTask => &["k", "up"],
+            Action::CollapseTask => &["left"],
+            Action::ExpandTask => &["right"],
+            Action::FilterTasks => &["/"],
+            Action::AddTask => &["a"],
+            Action::AddSubtask => &["A"],
+            Action::EditTask => &["e"],
+            Action::DeleteTask => &["d"],
+            Action::ActivateTask => &["t"],
+            Action::SaveTasks => &["S"],
+            Action::ToggleTaskCompleted => &["enter"],
+            Action::AddPomodoro => &["+"],
+            Action::RemovePomodoro => &["-"],
+            Action::ToggleHelp => &["?"],
+            Action::Quit => &["q"],
+        }
+    }
+}
+
+/// A single key press along with the modifiers held down.
+#[derive(Clone, Copy, PartialEq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of the character, e.g. `S` rather than `shift+s`
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = String;
+
+    /// Parse a key such as `q`, `S`, `space`, `ctrl+s` or `alt+enter`.
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid key `{}`", key);
+
+        // Split on the last `+` so `+` and `ctrl++` can be bound
+        let (modifiers, name) = match key.rsplit_once('+') {
+            Some(("", "")) => ("", "+"),
+            Some((modifiers, "")) => (modifiers.strip_suffix('+').ok_or_else(invalid)?, "+"),
+            Some((modifiers, name)) => (modifiers, name),
+            None => ("", key),
+        };
+
+        let mut modifier_flags = KeyModifiers::NONE;
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            modifier_flags |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+
+        let code = match name.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            lower => match lower.strip_prefix('f').map(str::parse::<u8>) {
+                Some(Ok(number)) if (1..=12).contains(&number) => KeyCode::F(number),
+                _ => {
+                    let mut chars = name.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if modifier_flags.contains(KeyModifiers::SHIFT) => {
+                            KeyCode::Char(c.to_ascii_uppercase())
+                        }
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => return Err(invalid()),
+                    }
+                }
+            },
+        };
+
+        Ok(Self::new(code, modifier_flags))
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// A key, or a chord of keys pressed one after the other such as `g g`.
+#[derive(Clone, PartialEq)]
+pub struct KeyBinding(pub Vec<KeyPress>);
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(binding: &str) -> Result<Self, Self::Err> {
+        let keys = binding
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<KeyPress>, _>>()?;
+
+        if keys.is_empty() {
+            return Err(String::from("empty key binding"));
+        }
+
+        Ok(Self(keys))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(KeyPress::to_string).collect();
+        write!(f, "{}", keys.join(" "))
+    }
+}
+
+/// Maps key presses to actions, keeping track of a chord that's part way through being typed.
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Action)>,
+    pending: Vec<KeyPress>,
+}
+
+impl Keymap {
+    /// Build the keymap from the `[keys]` config table. Actions that aren't listed keep their
+    /// default keys, and an empty list unbinds an action.
+    pub fn new(keys: &BTreeMap<Action, Vec<String>>) -> Result<Self, String> {
+        let mut bindings = vec![];
+
+        for action in Action::ALL {
+            match keys.get(&action) {
+                Some(configured) => {
+                    for binding in configured {
+                        bindings.push((binding.parse::<KeyBinding>()?, action));
+                    }
+                }
+                None => {
+                    for binding in action.default_keys() {
+                        bindings.push((binding.parse::<KeyBinding>()?, action));
+                    }
+                }
+            }
+        }
+
+        // A binding that starts another would make the longer one impossible to type
+        for (index, (binding, action)) in bindings.iter().enumerate() {
+            for (other, other_action) in &bindings[index + 1..] {
+                if binding.0.starts_with(&other.0) || other.0.starts_with(&binding.0) {
+                    return Err(format!(
+                        "`{}` ({}) conflicts with `{}` ({})",
+                        binding, action, other, other_action
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            bindings,
+            pending: vec![],
+        })
+    }
+
+    /// Feed in a key press, returning the action once a binding has been completed. A key that
+    /// doesn't continue the pending chord is tried again on its own.
+    pub fn handle(&mut self, key: KeyPress) -> Option<Action> {
+        self.pending.push(key);
+
+        let mut is_prefix = false;
+        for (binding, action) in &self.bindings {
+            if binding.0 == self.pending {
+                self.pending.clear();
+                return Some(*action);
+            }
+
+            is_prefix |= binding.0.starts_with(&self.pending);
+        }
+
+        if is_prefix {
+            return None;
+        }
+
+        let was_chord = self.pending.len() > 1;
+        self.pending.clear();
+
+        if was_chord {
+            self.handle(key)
+        } else {
+            None
+        }
+    }
+
+    /// The keys of a chord typed so far.
+    pub fn pending(&self) -> Option<KeyBinding> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(KeyBinding(self.pending.clone()))
+        }
+    }
+
+    /// Every binding for `action` as shown to the user, e.g. `j, Down`.
+    pub fn label(&self, action: Action) -> String {
+        let keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(binding, _)| binding.to_string())
+            .collect();
+
+        keys.join(", ")
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("default key bindings are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> KeyPress {
+        key.parse().unwrap()
+    }
+
+    fn keymap(keys: &[(Action, &[&str])]) -> Result<Keymap, String> {
+        let keys = keys
+            .iter()
+            .map(|(action, bindings)| (*action, bindings.iter().map(|b| b.to_string()).collect()))
+            .collect();
+
+        Keymap::new(&keys)
+    }
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        assert!(key("q") == KeyPress::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert!(key("ctrl+s") == KeyPress::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert!(
+            key("Ctrl+Alt+Enter")
+                == KeyPress::new(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT)
+        );
+        assert!(key("space") == KeyPress::new(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert!(key("f12") == KeyPress::new(KeyCode::F(12), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn parses_plus_on_its_own_and_with_modifiers() {
+        assert!(key("+") == KeyPress::new(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert!(key("ctrl++") == KeyPress::new(KeyCode::Char('+'), KeyModifiers::CONTROL));
+    }
+
+    #[test]
+    fn shift_is_folded_into_characters() {
+        assert!(key("shift+s") == key("S"));
+        assert!(key("S").modifiers == KeyModifiers::NONE);
+        assert!(KeyPress::from(KeyEvent::new(KeyCode::Char('S'), KeyModifiers::SHIFT)) == key("S"));
+        assert!(key("shift+tab").modifiers == KeyModifiers::SHIFT);
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        for invalid in ["", "ctrl+", "hyper+a", "foo", "f13"] {
+            assert_eq!(
+                invalid.parse::<KeyPress>().err(),
+                Some(format!("invalid key `{}`", invalid))
+            );
+        }
+        assert_eq!(
+            " ".parse::<KeyBinding>().err(),
+            Some(String::from("empty key binding"))
+        );
+    }
+
+    #[test]
+    fn displays_keys_as_shown_in_help() {
+        assert_eq!(key("ctrl+s").to_string(), "Ctrl+s");
+        assert_eq!(key("space").to_string(), "Space");
+        assert_eq!(key("pagedown").to_string(), "PageDown");
+        assert_eq!("g g".parse::<KeyBinding>().unwrap().to_string(), "g g");
+    }
+
+    #[test]
+    fn default_keys_map_to_their_actions() {
+        let mut keymap = Keymap::default();
+
+        assert_eq!(keymap.handle(key("q")), Some(Action::Quit));
+        assert_eq!(keymap.handle(key("down")), Some(Action::NextTask));
+        assert_eq!(keymap.handle(key("x")), None);
+        assert_eq!(keymap.label(Action::NextTask), "j, Down");
+    }
+
+    #[test]
+    fn chords_wait_for_their_last_key() {
+        let mut keymap = keymap(&[(Action::Quit, &["g q"])]).unwrap();
+
+        assert_eq!(keymap.handle(key("g")), None);
+        assert_eq!(keymap.pending().unwrap().to_string(), "g");
+        assert_eq!(keymap.handle(key("q")), Some(Action::Quit));
+        assert!(keymap.pending().is_none());
+    }
+
+    #[test]
+    fn key_that_breaks_a_chord_is_tried_on_its_own() {
+        let mut keymap = keymap(&[(Action::Quit, &["g q"])]).unwrap();
+
+        assert_eq!(keymap.handle(key("g")), None);
+        assert_eq!(keymap.handle(key("p")), Some(Action::Pomodoro));
+        assert!(keymap.pending().is_none());
+    }
+
+    #[test]
+    fn empty_list_unbinds_an_action() {
+        let mut keymap = keymap(&[(Action::Quit, &[])]).unwrap();
+
+        assert_eq!(keymap.handle(key("q")), None);
+        assert_eq!(keymap.label(Action::Quit), "");
+    }
+
+    #[test]
+    fn rejects_bindings_that_start_one_another() {
+        assert_eq!(
+            keymap(&[(Action::Quit, &["g"]), (Action::Pomodoro, &["g p"])]).err(),
+            Some(String::from("`g p` (pomodoro) conflicts with `g` (quit)"))
+        );
+        assert_eq!(
+            keymap(&[(Action::Quit, &["p"])]).err(),
+            Some(String::from("`p` (pomodoro) conflicts with `p` (quit)"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_configured_keys() {
+        assert_eq!(
+            keymap(&[(Action::Quit, &["ctrl+nope"])]).err(),
+            Some(String::from("invalid key `ctrl+nope`"))
+        );
+    }
+}
@@ -1,102 +1,763 @@
+pub mod config;
+#[cfg(unix)]
+pub mod control;
+pub mod font;
+pub mod hooks;
+pub mod keymap;
 pub mod models;
+pub mod notifications;
+pub mod paths;
+pub mod screen;
+pub mod theme;
 pub mod ui;
+pub mod watcher;
 
-use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode};
+use config::{Autosave, Config, FlowtimeConfig};
+#[cfg(unix)]
+use control::{ControlCommand, ControlServer, Status};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEvent,
+    MouseEventKind,
+};
 use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
+use hooks::{HookEvent, Hooks};
+use keymap::{Action, KeyPress, Keymap};
+use models::cycle::{Cycle, Period, Profile};
+use models::filter::TaskFilter;
+use models::history::{unix_seconds, History, SessionOutcome, SessionRecord};
+use models::input_mode::{InputMode, TaskForm};
 use models::pomodoro_mode::PomodoroMode;
+use models::saved_state::{SavedState, StateFile};
 use models::stateful_list::StatefulList;
+use models::stats::{Stats, StatsGrouping};
 use models::study_mode::StudyMode;
 use models::task::Task;
 use models::timer::{Timer, TimerStatus};
+use notifications::{Notification, Notifications};
 
-use clap::Parser;
+use chrono::Local;
+use clap::{Parser, Subcommand};
+use font::{Font, SMALL_FONT};
+use screen::ClickAreas;
 use std::error::Error;
 use std::io;
-use std::path::Path;
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant, SystemTime};
+use theme::Theme;
 use tui::backend::{Backend, CrosstermBackend};
 use tui::Terminal;
 use ui::ui;
+use watcher::FileWatcher;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Pomodoro timer length.
-    #[arg(long, short, default_value_t = 25)]
-    pub pomodoro_time: u64,
-    /// Short break timer length.
-    #[arg(long, short, default_value_t = 5)]
-    pub short_break_time: u64,
-    /// Long break timer length.
-    #[arg(long, short, default_value_t = 15)]
-    pub long_break_time: u64,
-    /// Path to tasks file.
-    #[arg(long, short, default_value_t = String::from("tasks"))]
-    pub task_file_path: String,
-    /// Whether to open the application in focus mode.
-    #[arg(long, short, default_value_t = String::from("false"))]
-    pub focus_mode: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Path to the config file [default: $XDG_CONFIG_HOME/pomodoro/config.toml].
+    #[arg(long, short)]
+    pub config: Option<String>,
+    /// Pomodoro timer length [default: 25].
+    #[arg(long, short, value_parser = clap::value_parser!(u64).range(1..=config::MAX_DURATION))]
+    pub pomodoro_time: Option<u64>,
+    /// Short break timer length [default: 5].
+    #[arg(long, short, value_parser = clap::value_parser!(u64).range(1..=config::MAX_DURATION))]
+    pub short_break_time: Option<u64>,
+    /// Long break timer length [default: 15].
+    #[arg(long, short, value_parser = clap::value_parser!(u64).range(1..=config::MAX_DURATION))]
+    pub long_break_time: Option<u64>,
+    /// Path to tasks file [default: tasks].
+    #[arg(long, short)]
+    pub task_file_path: Option<String>,
+    /// Whether to open the application in focus mode [default: false].
+    #[arg(long, short)]
+    pub focus_mode: Option<String>,
+    /// Number of pomodoros before a long break [default: 4].
+    #[arg(long, short = 'i', value_parser = clap::value_parser!(u64).range(1..=config::MAX_LONG_BREAK_INTERVAL))]
+    pub long_break_interval: Option<u64>,
+    /// Path to the session history file [default: $XDG_DATA_HOME/pomodoro/history].
+    #[arg(long)]
+    pub history_file_path: Option<String>,
+    /// Name of the profile to start with [default: default].
+    #[arg(long)]
+    pub profile: Option<String>,
+    /// Font to draw the timer in: standard, small, block or a path to a .flf file [default: standard].
+    #[arg(long)]
+    pub font: Option<String>,
+    /// Theme to use: dark, light, a theme name or a path to a theme file [default: dark].
+    #[arg(long)]
+    pub theme: Option<String>,
+    /// Keep counting past zero until the next timer is started with `n`.
+    #[arg(long)]
+    pub overtime: bool,
+    /// Use the mouse to control the timer and select tasks.
+    #[arg(long)]
+    pub mouse: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the state of the running timer, as JSON unless a format is given.
+    /// Exits with code 3 if no instance is running.
+    Status {
+        /// Format string, e.g. "{mode} {mm_ss}". Placeholders: {mode}, {status},
+        /// {time_remaining}, {overtime}, {mm_ss}, {hh_mm_ss}, {percentage} and {task}.
+        #[arg(long)]
+        format: Option<String>,
+    },
 }
 
+/// Longest gap between the two clicks of a double-click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
 pub struct AppState {
     timer: Timer,
+    cycle: Cycle,
+    profiles: Vec<Profile>,
     study_mode: StudyMode,
     tasks: StatefulList<Task>,
+    /// Index of the task finished pomodoros are credited to, independent of the list cursor.
+    active_task: Option<usize>,
+    task_file: PathBuf,
+    task_watcher: FileWatcher,
+    autosave: Autosave,
+    /// Set when the task list has changes that haven't been written to the task file.
+    tasks_dirty: bool,
+    history: History,
+    state_file: StateFile,
+    hooks: Hooks,
+    notifications: Notifications,
+    flowtime: FlowtimeConfig,
+    stats: Stats,
+    stats_grouping: StatsGrouping,
+    input_mode: InputMode,
+    keymap: Keymap,
+    theme: Theme,
+    /// The font the timer is drawn in, followed by the one to fall back to when it doesn't fit.
+    fonts: Vec<Font>,
     show_help_menu: bool,
+    /// Lines scrolled down in the help menu, for screens too small to show all of it.
+    help_scroll: u16,
+    /// Whether the mouse is captured, see `ui.mouse`.
+    mouse: bool,
+    click_areas: ClickAreas,
+    /// First task shown in the task list, kept in step with the list's own scrolling.
+    tasks_offset: usize,
+    /// When and which task was last clicked, to spot double-clicks.
+    last_click: Option<(Instant, usize)>,
+    /// The query in the task list's filter bar, see `TaskFilter`.
+    task_filter: String,
+    /// Set when the task file couldn't be loaded, so it isn't overwritten with an empty list.
+    tasks_load_failed: bool,
+    error_message: Option<String>,
+    should_quit: bool,
 }
 
 impl AppState {
-    pub fn new(arguments: &Args) -> Self {
-        Self {
-            timer: Timer::new(
-                Duration::from_secs(arguments.pomodoro_time * 60),
-                PomodoroMode::Pomodoro,
+    pub fn new(config: &Config) -> Self {
+        let (tasks, error_message, tasks_load_failed) = match Task::from_file(&config.task_file()) {
+            Ok(tasks) => (tasks, None, false),
+            // A missing task file is created on the first save, but one the user asked for by
+            // name may well be a typo so they're told about it
+            Err(err) if err.is_not_found() => {
+                let error_message = config
+                    .tasks
+                    .file
+                    .is_some()
+                    .then(|| format!("Failed to load tasks: {}", err));
+                (vec![], error_message, false)
+            }
+            Err(err) => (vec![], Some(format!("Failed to load tasks: {}", err)), true),
+        };
+
+        let cycle = Cycle::new(config.active_profile());
+        let state_file = StateFile::new(config.state_file());
+
+        // Only offer to resume a timer that had actually been started
+        let (input_mode, error_message) = match state_file.load() {
+            Ok(Some(saved)) if saved.timer.started_at.is_some() => {
+                (InputMode::ConfirmResume(saved), error_message)
+            }
+            Ok(_) => (InputMode::Normal, error_message),
+            Err(err) => (
+                InputMode::Normal,
+                error_message.or(Some(format!("Failed to load the saved timer: {}", err))),
             ),
-            study_mode: match arguments.focus_mode.to_lowercase().as_str() {
-                "true" => StudyMode::Zen,
-                _ => StudyMode::Normal,
+        };
+
+        Self {
+            timer: cycle.current().timer(),
+            cycle,
+            profiles: config.profiles(),
+            study_mode: config.ui.start_mode,
+            tasks: StatefulList::with_items(tasks),
+            active_task: None,
+            task_file: config.task_file(),
+            task_watcher: FileWatcher::new(config.task_file()),
+            autosave: config.tasks.autosave,
+            tasks_dirty: false,
+            history: History::new(config.history_file()),
+            state_file,
+            hooks: config.hooks.clone(),
+            notifications: Notifications::from_config(&config.notifications),
+            flowtime: config.flowtime.clone(),
+            stats: Stats::default(),
+            stats_grouping: StatsGrouping::default(),
+            input_mode,
+            keymap: Keymap::new(&config.keys).unwrap_or_default(),
+            theme: config.theme.clone(),
+            fonts: if config.font.name == SMALL_FONT {
+                vec![config.font.clone()]
+            } else {
+                vec![config.font.clone(), Font::small()]
             },
-            // Todo: turn path to const
-            tasks: StatefulList::with_items(Task::from_file(Path::new(&arguments.task_file_path))),
             show_help_menu: false,
+            help_scroll: 0,
+            mouse: config.ui.mouse,
+            click_areas: ClickAreas::default(),
+            tasks_offset: 0,
+            last_click: None,
+            task_filter: String::new(),
+            tasks_load_failed,
+            error_message,
+            should_quit: false,
+        }
+    }
+}
+
+impl AppState {
+    fn active_task_title(&self) -> Option<String> {
+        self.active_task
+            .map(|active| self.tasks.items[active].title.clone())
+    }
+
+    /// Only show the tasks matching `task_filter`, or every task if it's empty.
+    fn apply_task_filter(&mut self) {
+        let filter = TaskFilter::parse(&self.task_filter);
+
+        self.tasks.set_filter(if filter.is_empty() {
+            None
+        } else {
+            Some(Box::new(move |task| filter.matches(task)))
+        });
+    }
+
+    /// Note that the task list has changed, saving it straight away when autosaving on change.
+    fn tasks_changed(&mut self) {
+        self.tasks_dirty = true;
+        // The change may mean the filter no longer matches the selected task
+        self.tasks.refresh();
+
+        if self.autosave == Autosave::OnChange {
+            self.autosave_tasks();
+        }
+    }
+
+    /// Save the task list if it has unsaved changes. Skipped rather than reported when the
+    /// task file couldn't be loaded, as `S` already explains why it won't be overwritten.
+    fn autosave_tasks(&mut self) {
+        if self.tasks_dirty && !self.tasks_load_failed {
+            self.save_tasks();
+        }
+    }
+
+    /// Write the task list to the task file. Returns whether it was saved.
+    fn save_tasks(&mut self) -> bool {
+        if self.tasks_load_failed {
+            self.error_message = Some(String::from(
+                "Tasks failed to load, refusing to overwrite the task file",
+            ));
+            return false;
+        }
+
+        match Task::save(&self.task_file, &self.tasks.items) {
+            Ok(()) => {
+                self.task_watcher.mark_seen();
+                self.tasks_dirty = false;
+                true
+            }
+            Err(err) => {
+                self.error_message = Some(format!(
+                    "Failed to save tasks to {}: {}",
+                    self.task_file.display(),
+                    err
+                ));
+                false
+            }
+        }
+    }
+
+    /// Reload the task list if the task file was changed by another program. Unsaved changes
+    /// aren't thrown away without asking first.
+    fn check_task_file(&mut self) {
+        if !self.task_watcher.changed() {
+            return;
+        }
+
+        if self.tasks_dirty {
+            self.input_mode = InputMode::ConfirmReload;
+        } else {
+            self.reload_tasks();
+        }
+    }
+
+    /// Replace the task list with the task file's contents, keeping the selected and active
+    /// tasks by their title and those of the tasks they're nested under. A task that can't be
+    /// told apart from another that way isn't kept, rather than risk picking the wrong one.
+    fn reload_tasks(&mut self) {
+        let tasks = match Task::from_file(&self.task_file) {
+            Ok(tasks) => tasks,
+            Err(err) => {
+                self.error_message = Some(format!("Failed to reload tasks: {}", err));
+                return;
+            }
+        };
+
+        let selected = self
+            .tasks
+            .selected()
+            .map(|index| Task::path(&self.tasks.items, index));
+        let active = self
+            .active_task
+            .map(|index| Task::path(&self.tasks.items, index));
+        let find = |path: Option<Vec<String>>| Task::find_by_path(&tasks, &path?);
+
+        let selected = find(selected).or(if tasks.is_empty() { None } else { Some(0) });
+        self.active_task = find(active);
+        self.tasks.replace_items(tasks, selected);
+        self.tasks_dirty = false;
+        self.tasks_load_failed = false;
+    }
+
+    /// Save the running timer and stop the application.
+    fn quit(&mut self) {
+        // A saved timer is logged once it's resumed or discarded
+        if !self.save_state() {
+            self.log_timer(SessionOutcome::Quit);
+        }
+
+        self.should_quit = true;
+    }
+
+    /// Make the task at `index` the active one, or clear it if it already is.
+    fn toggle_active_task(&mut self, index: usize) {
+        self.active_task = match self.active_task {
+            Some(active) if active == index => None,
+            _ => Some(index),
+        };
+    }
+
+    /// Select a clicked task, marking it complete or not when it's clicked twice in a row.
+    fn click_task(&mut self, index: usize) {
+        if !self.tasks.select(index) {
+            return;
+        }
+
+        let now = Instant::now();
+        let double_click = matches!(
+            self.last_click,
+            Some((clicked_at, clicked)) if clicked == index && now - clicked_at <= DOUBLE_CLICK_TIME
+        );
+
+        if double_click {
+            self.toggle_task_completed(index);
+            self.last_click = None;
+        } else {
+            self.last_click = Some((now, index));
+        }
+    }
+
+    /// Delete the task at `index` along with its subtasks, keeping the active task pointing at
+    /// the same task.
+    fn delete_task(&mut self, index: usize) {
+        let parent = self.tasks.parent(index);
+        let removed = self.tasks.delete(index);
+
+        self.active_task = match self.active_task {
+            Some(active) if removed.contains(&active) => None,
+            Some(active) if active >= removed.end => Some(active - removed.len()),
+            active => active,
+        };
+        if let Some(parent) = parent {
+            self.update_parents(parent);
+        }
+        self.tasks_changed();
+    }
+
+    /// Add `task` as the last subtask of the task at `parent` and select it.
+    fn add_subtask(&mut self, parent: usize, mut task: Task) {
+        let index = self.tasks.subtree(parent).end;
+        task.depth = self.tasks.items[parent].depth + 1;
+
+        self.tasks.insert(task, index);
+        self.tasks.select(index);
+        self.active_task = match self.active_task {
+            Some(active) if active >= index => Some(active + 1),
+            active => active,
+        };
+        self.update_parents(parent);
+        self.tasks_changed();
+    }
+
+    /// Run the hook configured for `event`, reporting any failure to start it.
+    fn run_hook(&mut self, event: HookEvent, task: Option<String>) {
+        if let Err(err) = self.hooks.run(event, &self.timer, task.as_deref()) {
+            self.error_message = Some(format!("Failed to run {} hook: {}", event.name(), err));
+        }
+    }
+
+    fn notify(&mut self, notification: Notification) {
+        if let Some(err) = self.notifications.notify(&notification).first() {
+            self.error_message = Some(format!("Failed to send notification: {}", err));
+        }
+    }
+
+    /// Record the current timer run in the session history.
+    fn log_timer(&mut self, outcome: SessionOutcome) {
+        let task = self.active_task.map(|active| &self.tasks.items[active]);
+
+        if let Some(record) = SessionRecord::from_timer(&self.timer, outcome, task) {
+            self.append_history(&record);
+        }
+    }
+
+    fn append_history(&mut self, record: &SessionRecord) {
+        if let Err(err) = self.history.append(record) {
+            self.error_message = Some(format!("Failed to write session history: {}", err));
+        }
+    }
+
+    /// Save the running timer so it can be resumed after a restart. Returns whether it was
+    /// saved.
+    fn save_state(&mut self) -> bool {
+        let state = SavedState {
+            saved_at: SystemTime::now(),
+            timer: self.timer.snapshot(),
+            profile: self.cycle.profile.name.clone(),
+            position: self.cycle.position,
+            task: self.active_task_title(),
+            task_path: self
+                .active_task
+                .map(|active| Task::path(&self.tasks.items, active)),
+        };
+
+        match self.state_file.save(&state) {
+            Ok(()) => true,
+            Err(err) => {
+                self.error_message = Some(format!("Failed to save the timer: {}", err));
+                false
+            }
+        }
+    }
+
+    /// Pick up the saved timer, counting the time since it was saved if it was running.
+    fn resume_saved(&mut self, saved: SavedState) {
+        let Some(profile) = self
+            .profiles
+            .iter()
+            .find(|profile| profile.name == saved.profile)
+        else {
+            self.error_message = Some(format!(
+                "Can't resume the timer, profile `{}` no longer exists",
+                saved.profile
+            ));
+            self.discard_saved(saved);
+            return;
+        };
+
+        self.cycle = Cycle::new(profile.clone());
+        if saved.position < self.cycle.profile.periods.len() {
+            self.cycle.position = saved.position;
+        }
+
+        self.active_task = self.saved_task(&saved);
+
+        self.timer = Timer::restore(&saved.timer, saved.time_away());
+    }
+
+    /// Find the saved active task in the task list. State saved by older versions only has the
+    /// title, which is taken to be a top level task.
+    fn saved_task(&self, saved: &SavedState) -> Option<usize> {
+        let path = saved
+            .task_path
+            .clone()
+            .or_else(|| saved.task.clone().map(|title| vec![title]))?;
+
+        Task::find_by_path(&self.tasks.items, &path)
+    }
+
+    /// Log the saved timer as having ended when the application was closed.
+    fn discard_saved(&mut self, saved: SavedState) {
+        let timer = Timer::restore(&saved.timer, Duration::ZERO);
+        let task = self
+            .saved_task(&saved)
+            .map(|index| &self.tasks.items[index]);
+
+        if let Some(mut record) = SessionRecord::from_timer(&timer, SessionOutcome::Quit, task) {
+            record.ended_at = unix_seconds(saved.saved_at);
+            // Keep the title even if the task has since gone from the list
+            record.task = saved.task;
+            self.append_history(&record);
+        }
+    }
+
+    /// Recalculate the statistics from the session history.
+    fn refresh_stats(&mut self) {
+        match self.history.load() {
+            Ok(records) => {
+                self.stats = Stats::new(&records, &self.tasks.items, Local::now().date_naive())
+            }
+            Err(err) => {
+                self.error_message = Some(format!("Failed to read session history: {}", err))
+            }
+        }
+    }
+
+    /// Swap in a new timer, logging the current one with the given outcome. A timer that ran
+    /// all the way down counts as finished however it's replaced.
+    fn replace_timer(&mut self, timer: Timer, outcome: SessionOutcome) {
+        let outcome = if self.timer.is_finished() {
+            self.credit_finished_pomodoro();
+            SessionOutcome::Finished
+        } else {
+            outcome
+        };
+
+        if self.timer.started_at().is_some() {
+            self.run_hook(
+                HookEvent::end_of(self.timer.pomodoro_mode),
+                self.active_task_title(),
+            );
+        }
+
+        self.log_timer(outcome);
+        self.timer = timer;
+    }
+
+    fn pause_timer(&mut self) {
+        if self.timer.status == TimerStatus::Playing {
+            self.timer.pause();
+            self.run_hook(HookEvent::Pause, self.active_task_title());
+        }
+    }
+
+    fn resume_timer(&mut self) {
+        if self.timer.status == TimerStatus::Paused {
+            let event = match self.timer.started_at() {
+                Some(_) => HookEvent::Resume,
+                None => HookEvent::start_of(self.timer.pomodoro_mode),
+            };
+
+            self.timer.unpause();
+            self.run_hook(event, self.active_task_title());
+        }
+    }
+
+    fn toggle_timer(&mut self) {
+        match self.timer.status {
+            TimerStatus::Paused => self.resume_timer(),
+            TimerStatus::Playing => self.pause_timer(),
+        }
+    }
+
+    /// Restart the current timer from the beginning.
+    fn reset_timer(&mut self) {
+        self.replace_timer(self.timer.restarted(), SessionOutcome::Reset);
+    }
+
+    /// Skip to the next period in the given mode, or a one-off timer if the profile has none.
+    fn switch_mode(&mut self, config: &Config, mode: PomodoroMode) {
+        let timer = match self.cycle.jump_to(mode) {
+            Some(period) => period_timer(config, period),
+            None => config.timer_for(mode),
+        };
+
+        self.replace_timer(timer, SessionOutcome::Skipped);
+    }
+
+    /// Start over with the given profile.
+    fn switch_profile(&mut self, config: &Config, profile: Profile) {
+        self.cycle = Cycle::new(profile);
+        self.replace_timer(
+            period_timer(config, self.cycle.current()),
+            SessionOutcome::Skipped,
+        );
+    }
+
+    /// Whether the current timer is the cycle's current period, rather than a one-off.
+    fn timer_in_cycle(&self) -> bool {
+        self.timer.pomodoro_mode == self.cycle.current().mode
+    }
+
+    /// The timer that follows the current one. One-off timers are followed by the period they
+    /// interrupted, and flowtime by the break it earned.
+    fn next_timer(&self, config: &Config) -> Timer {
+        let period = if self.timer_in_cycle() {
+            self.cycle.next()
+        } else {
+            self.cycle.current()
+        };
+
+        if self.timer.pomodoro_mode == PomodoroMode::Flowtime {
+            let mode = if period.mode.is_focus() {
+                PomodoroMode::ShortBreak
+            } else {
+                period.mode
+            };
+
+            Timer::new(self.flowtime.break_for(self.timer.elapsed()), mode)
+        } else {
+            period_timer(config, period)
+        }
+    }
+
+    /// Let the user know the current timer has run out.
+    fn notify_finished(&mut self, next_mode: PomodoroMode) {
+        let notification = Notification::period_finished(
+            self.timer.pomodoro_mode,
+            next_mode,
+            self.active_task_title().as_deref(),
+        );
+
+        self.notify(notification);
+    }
+
+    /// Move on to the next timer in the cycle and start it.
+    fn advance_cycle(&mut self, config: &Config) {
+        let next_timer = self.next_timer(config);
+
+        if self.timer_in_cycle() {
+            self.cycle.advance();
+        }
+
+        if self.timer.is_finished() && !self.timer.overtime_notified {
+            self.notify_finished(next_timer.pomodoro_mode);
+        }
+
+        self.replace_timer(next_timer, SessionOutcome::Finished);
+        self.resume_timer();
+    }
+
+    /// Credit the active task with the current timer if it's a pomodoro that ran all the way
+    /// down.
+    fn credit_finished_pomodoro(&mut self) {
+        if self.timer.pomodoro_mode == PomodoroMode::Pomodoro && self.timer.is_finished() {
+            if let Some(active) = self.active_task {
+                self.tasks.items[active].complete_pomodoro();
+                self.tasks_changed();
+            }
+        }
+    }
+
+    /// Mark or unmark the task at `index` as complete.
+    fn toggle_task_completed(&mut self, index: usize) {
+        if let Some(task) = self.tasks.items.get_mut(index) {
+            task.complete_task();
+
+            if task.completed {
+                let title = task.title.clone();
+                self.run_hook(HookEvent::TaskCompleted, Some(title));
+            }
+
+            if let Some(parent) = self.tasks.parent(index) {
+                self.update_parents(parent);
+            }
+            self.tasks_changed();
+        }
+    }
+
+    /// Mark the task at `index` and each of its parents as complete once all of their subtasks
+    /// are, and as not complete while any aren't.
+    fn update_parents(&mut self, index: usize) {
+        let mut parent = Some(index);
+
+        while let Some(index) = parent {
+            let completed = self
+                .tasks
+                .children(index)
+                .all(|child| self.tasks.items[child].completed);
+
+            // A task that's lost its last subtask keeps its own state
+            if self.tasks.has_children(index) && self.tasks.items[index].completed != completed {
+                self.tasks.items[index].completed = completed;
+
+                if completed {
+                    let title = self.tasks.items[index].title.clone();
+                    self.run_hook(HookEvent::TaskCompleted, Some(title));
+                }
+            }
+
+            parent = self.tasks.parent(index);
         }
     }
 }
 
+/// The timer for a period. Breaks following flowtime have no length of their own, so outside of
+/// flowtime they fall back to the lengths under `[timer]`.
+fn period_timer(config: &Config, period: &Period) -> Timer {
+    if period.mode != PomodoroMode::Flowtime && period.duration == 0 {
+        config.timer_for(period.mode)
+    } else {
+        period.timer()
+    }
+}
+
 impl Default for AppState {
     fn default() -> Self {
-        Self {
-            timer: Timer::new(Duration::from_secs(25 * 60), PomodoroMode::Pomodoro),
-            study_mode: StudyMode::Normal,
-            // Todo: turn path to const
-            tasks: StatefulList::with_items(Task::from_file(Path::new("tasks"))),
-            show_help_menu: false,
-        }
+        Self::new(&Config::default())
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
+    let config = match Config::load(&args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    if let Some(Command::Status { format }) = &args.command {
+        #[cfg(unix)]
+        process::exit(control::print_status(
+            &config.control_socket(),
+            format.as_deref(),
+        ));
+
+        #[cfg(not(unix))]
+        {
+            let _ = format;
+            eprintln!("status is only supported on Unix");
+            process::exit(1);
+        }
+    }
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen)?;
+    // Only take over the mouse when asked to, so text can still be selected in the terminal
+    if config.ui.mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let tick_rate = Duration::from_secs(1);
-    let app_state = AppState::new(&args);
-    let res = run_app(&mut terminal, app_state, tick_rate, args);
+    // The timer is measured against the wall clock, this only controls how often it's redrawn
+    let tick_rate = config.tick_rate();
+    let mouse = config.ui.mouse;
+    let app_state = AppState::new(&config);
+    let res = run_app(&mut terminal, app_state, tick_rate, config);
 
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    if mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
     if let Err(err) = res {
@@ -110,10 +771,26 @@ fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     mut app_state: AppState,
     tick_rate: Duration,
-    args: Args,
+    config: Config,
 ) -> Result<(), Box<dyn Error>> {
     app_state.tasks.next();
     let mut last_tick = Instant::now();
+    let mut last_save = Instant::now();
+    let mut last_autosave = Instant::now();
+
+    #[cfg(unix)]
+    let control_server = if config.control.enabled {
+        match ControlServer::bind(&config.control_socket()) {
+            Ok(control_server) => Some(control_server),
+            Err(err) => {
+                app_state.error_message =
+                    Some(format!("Failed to start the control socket: {}", err));
+                None
+            }
+        }
+    } else {
+        None
+    };
 
     loop {
         terminal.draw(|f| ui(f, &mut app_state))?;
@@ -123,99 +800,426 @@ fn run_app<B: Backend>(
             .unwrap_or_else(|| Duration::from_secs(0));
 
         if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    // Timer interaction keys
-                    KeyCode::Char(' ') => match app_state.timer.status {
-                        TimerStatus::Paused => app_state.timer.unpause(),
-                        TimerStatus::Playing => app_state.timer.pause(),
-                    },
-                    KeyCode::Char('r') => match app_state.timer.pomodoro_mode {
-                        PomodoroMode::Pomodoro => {
-                            app_state.timer = Timer::new(
-                                Duration::from_secs(args.pomodoro_time * 60),
-                                PomodoroMode::Pomodoro,
-                            )
-                        }
-                        PomodoroMode::LongBreak => {
-                            app_state.timer = Timer::new(
-                                Duration::from_secs(args.long_break_time * 60),
-                                PomodoroMode::LongBreak,
-                            )
-                        }
-                        PomodoroMode::ShortBreak => {
-                            app_state.timer = Timer::new(
-                                Duration::from_secs(args.short_break_time * 60),
-                                PomodoroMode::ShortBreak,
-                            )
-                        }
-                    },
-                    KeyCode::Char('f') => match app_state.study_mode {
-                        StudyMode::Normal => app_state.study_mode = StudyMode::Zen,
-                        StudyMode::Zen => app_state.study_mode = StudyMode::Normal,
-                    },
-
-                    // List interaction keys
-                    KeyCode::Char('k') | KeyCode::Up => app_state.tasks.previous(),
-                    KeyCode::Char('j') | KeyCode::Down => app_state.tasks.next(),
-                    KeyCode::Enter => {
-                        if let Some(selected) = app_state.tasks.selected() {
-                            app_state.tasks.items[selected].complete_task()
-                        }
+            match event::read()? {
+                Event::Key(key) => {
+                    // Any key dismisses an error
+                    if app_state.error_message.is_some() {
+                        app_state.error_message = None;
+                        continue;
                     }
-                    KeyCode::Char('+') => {
-                        if let Some(selected) = app_state.tasks.selected() {
-                            app_state.tasks.items[selected].complete_pomodoro()
-                        }
-                    }
-                    KeyCode::Char('-') => {
-                        if let Some(selected) = app_state.tasks.selected() {
-                            app_state.tasks.items[selected].negate_pomodoro()
-                        }
-                    }
-
-                    // IO interaction keys
-                    KeyCode::Char('S') => Task::save(Path::new("tasks"), &app_state.tasks.items)?,
 
-                    // Change Timer controls
-                    KeyCode::Char('p') => {
-                        app_state.timer = Timer::new(
-                            Duration::from_secs(args.pomodoro_time * 60),
-                            PomodoroMode::Pomodoro,
-                        )
-                    }
-                    KeyCode::Char('s') => {
-                        app_state.timer = Timer::new(
-                            Duration::from_secs(args.short_break_time * 60),
-                            PomodoroMode::ShortBreak,
-                        )
-                    }
-                    KeyCode::Char('l') => {
-                        app_state.timer = Timer::new(
-                            Duration::from_secs(args.long_break_time * 60),
-                            PomodoroMode::LongBreak,
-                        )
+                    if !matches!(app_state.input_mode, InputMode::Normal) {
+                        handle_input_key(&mut app_state, &config, key.code);
+                        continue;
                     }
 
-                    // Misc keys
-                    KeyCode::Char('?') => app_state.show_help_menu = !app_state.show_help_menu,
-                    KeyCode::Char('q') => return Ok(()),
-                    _ => {}
+                    if let Some(action) = app_state.keymap.handle(KeyPress::from(key)) {
+                        handle_action(&mut app_state, &config, action);
+                    }
+                }
+                // Popups are only driven by the keyboard
+                Event::Mouse(mouse)
+                    if app_state.error_message.is_none()
+                        && matches!(app_state.input_mode, InputMode::Normal) =>
+                {
+                    handle_mouse(&mut app_state, &config, mouse)
                 }
+                _ => {}
             }
         }
 
+        #[cfg(unix)]
+        if let Some(control_server) = &control_server {
+            control_server.poll(|command| handle_control_command(&mut app_state, &config, command));
+        }
+
+        if app_state.should_quit {
+            return Ok(());
+        }
+
         if last_tick.elapsed() >= tick_rate {
             match app_state.timer.status {
                 TimerStatus::Playing => {
-                    if app_state.timer.time_remaining.as_secs() != 0 {
-                        app_state.timer.tick()
+                    if app_state.timer.is_finished() {
+                        if !config.timer.overtime {
+                            // Move on to the next period in the cycle and keep it running
+                            app_state.advance_cycle(&config);
+                        } else if !app_state.timer.overtime_notified {
+                            let next_mode = app_state.next_timer(&config).pomodoro_mode;
+                            app_state.notify_finished(next_mode);
+                            app_state.timer.overtime_notified = true;
+                        }
                     }
                 }
                 TimerStatus::Paused => {}
             }
+
+            // Forms and prompts hold on to task indices, so wait until they're closed
+            if matches!(app_state.input_mode, InputMode::Normal) {
+                app_state.check_task_file();
+            }
+
+            last_tick = Instant::now();
+        }
+
+        // Leave the saved timer alone until the user has decided whether to resume it
+        if last_save.elapsed() >= config.save_interval()
+            && !matches!(app_state.input_mode, InputMode::ConfirmResume(_))
+        {
+            app_state.save_state();
+            last_save = Instant::now();
+        }
+
+        if app_state.autosave == Autosave::Interval
+            && last_autosave.elapsed() >= config.autosave_interval()
+        {
+            app_state.autosave_tasks();
+            last_autosave = Instant::now();
+        }
+    }
+}
+
+/// Handle a mouse event in the main view, matched against where things were last drawn.
+fn handle_mouse(app_state: &mut AppState, config: &Config, mouse: MouseEvent) {
+    let (column, row) = (mouse.column, mouse.row);
+
+    match mouse.kind {
+        MouseEventKind::ScrollDown if app_state.show_help_menu => app_state.help_scroll += 1,
+        MouseEventKind::ScrollUp if app_state.show_help_menu => {
+            app_state.help_scroll = app_state.help_scroll.saturating_sub(1)
+        }
+        MouseEventKind::ScrollDown if app_state.click_areas.tasks.is_some() => {
+            app_state.tasks.next()
+        }
+        MouseEventKind::ScrollUp if app_state.click_areas.tasks.is_some() => {
+            app_state.tasks.previous()
+        }
+        MouseEventKind::Down(MouseButton::Left) if !app_state.show_help_menu => {
+            if let Some(mode) = app_state.click_areas.mode_at(column, row) {
+                app_state.switch_mode(config, mode);
+            } else if let Some(index) = app_state
+                .click_areas
+                .task_row_at(column, row)
+                .and_then(|row| app_state.tasks.visible().get(row).copied())
+            {
+                app_state.click_task(index);
+            } else if app_state.click_areas.in_timer(column, row) {
+                app_state.toggle_timer();
+            }
         }
+        _ => {}
+    }
+}
+
+/// Carry out an action bound in the keymap.
+fn handle_action(app_state: &mut AppState, config: &Config, action: Action) {
+    match action {
+        // Timer actions
+        Action::ToggleTimer => app_state.toggle_timer(),
+        Action::ResetTimer => app_state.reset_timer(),
+        Action::ToggleZen => match app_state.study_mode {
+            StudyMode::Normal | StudyMode::Stats => app_state.study_mode = StudyMode::Zen,
+            StudyMode::Zen => app_state.study_mode = StudyMode::Normal,
+        },
+        Action::ToggleStats => match app_state.study_mode {
+            StudyMode::Normal | StudyMode::Zen => {
+                app_state.refresh_stats();
+                app_state.study_mode = StudyMode::Stats
+            }
+            StudyMode::Stats => app_state.study_mode = StudyMode::Normal,
+        },
+        Action::GroupStats => app_state.stats_grouping = app_state.stats_grouping.next(),
+        Action::Pomodoro => app_state.switch_mode(config, PomodoroMode::Pomodoro),
+        Action::ShortBreak => app_state.switch_mode(config, PomodoroMode::ShortBreak),
+        Action::LongBreak => app_state.switch_mode(config, PomodoroMode::LongBreak),
+        Action::Flowtime => app_state.switch_mode(config, PomodoroMode::Flowtime),
+        Action::NextTimer => {
+            if app_state.timer.is_finished() || app_state.timer.is_count_up() {
+                app_state.advance_cycle(config)
+            }
+        }
+        Action::PickProfile => {
+            let current = app_state
+                .profiles
+                .iter()
+                .position(|profile| profile.name == app_state.cycle.profile.name)
+                .unwrap_or(0);
+            app_state.input_mode = InputMode::PickingProfile(current)
+        }
+
+        // Task actions
+        // Scroll the help menu while it's open
+        Action::PreviousTask if app_state.show_help_menu => {
+            app_state.help_scroll = app_state.help_scroll.saturating_sub(1)
+        }
+        Action::NextTask if app_state.show_help_menu => app_state.help_scroll += 1,
+        Action::PreviousTask => app_state.tasks.previous(),
+        Action::NextTask => app_state.tasks.next(),
+        // Saved along with the tasks, but not a change worth asking about on its own
+        Action::CollapseTask => app_state.tasks.collapse(),
+        Action::ExpandTask => app_state.tasks.expand(),
+        // The filter bar lives in the task list, so bring it into view
+        Action::FilterTasks => {
+            app_state.study_mode = StudyMode::Normal;
+            app_state.input_mode = InputMode::FilteringTasks
+        }
+        Action::ToggleTaskCompleted => {
+            if let Some(selected) = app_state.tasks.selected() {
+                app_state.toggle_task_completed(selected)
+            }
+        }
+        Action::AddPomodoro => {
+            if let Some(selected) = app_state.tasks.selected() {
+                app_state.tasks.items[selected].complete_pomodoro();
+                app_state.tasks_changed();
+            }
+        }
+        Action::RemovePomodoro => {
+            if let Some(selected) = app_state.tasks.selected() {
+                app_state.tasks.items[selected].negate_pomodoro();
+                app_state.tasks_changed();
+            }
+        }
+        Action::ActivateTask => {
+            if let Some(selected) = app_state.tasks.selected() {
+                app_state.toggle_active_task(selected)
+            }
+        }
+        Action::AddTask => app_state.input_mode = InputMode::AddingTask(TaskForm::new()),
+        Action::AddSubtask => {
+            if let Some(selected) = app_state.tasks.selected() {
+                app_state.input_mode = InputMode::AddingSubtask(
+                    selected,
+                    TaskForm::for_subtask(&app_state.tasks.items[selected]),
+                )
+            }
+        }
+        Action::EditTask => {
+            if let Some(selected) = app_state.tasks.selected() {
+                app_state.input_mode = InputMode::EditingTask(
+                    selected,
+                    TaskForm::from_task(&app_state.tasks.items[selected]),
+                )
+            }
+        }
+        Action::DeleteTask => {
+            if let Some(selected) = app_state.tasks.selected() {
+                app_state.input_mode = InputMode::ConfirmDelete(selected)
+            }
+        }
+        Action::SaveTasks => {
+            app_state.save_tasks();
+        }
+
+        // Misc actions
+        Action::ToggleHelp => {
+            app_state.show_help_menu = !app_state.show_help_menu;
+            app_state.help_scroll = 0;
+        }
+        Action::Quit => {
+            if app_state.tasks_dirty {
+                app_state.input_mode = InputMode::ConfirmQuit
+            } else {
+                app_state.quit()
+            }
+        }
+    }
+}
+
+/// Handle a key press while one of the popups is open.
+fn handle_input_key(app_state: &mut AppState, config: &Config, key: KeyCode) {
+    let input_mode = std::mem::replace(&mut app_state.input_mode, InputMode::Normal);
+
+    app_state.input_mode = match input_mode {
+        InputMode::Normal => InputMode::Normal,
+        InputMode::AddingTask(mut form) => match key {
+            KeyCode::Esc => InputMode::Normal,
+            KeyCode::Enter if form.is_valid() => {
+                app_state.tasks.push(form.task());
+                app_state.tasks.select(app_state.tasks.items.len() - 1);
+                app_state.tasks_changed();
+
+                InputMode::Normal
+            }
+            _ => {
+                edit_task_form(&mut form, key);
+                InputMode::AddingTask(form)
+            }
+        },
+        InputMode::AddingSubtask(parent, mut form) => match key {
+            KeyCode::Esc => InputMode::Normal,
+            KeyCode::Enter if form.is_valid() => {
+                app_state.add_subtask(parent, form.task());
+
+                InputMode::Normal
+            }
+            _ => {
+                edit_task_form(&mut form, key);
+                InputMode::AddingSubtask(parent, form)
+            }
+        },
+        InputMode::EditingTask(index, mut form) => match key {
+            KeyCode::Esc => InputMode::Normal,
+            KeyCode::Enter if form.is_valid() => {
+                form.update(&mut app_state.tasks.items[index]);
+                app_state.tasks_changed();
+
+                InputMode::Normal
+            }
+            _ => {
+                edit_task_form(&mut form, key);
+                InputMode::EditingTask(index, form)
+            }
+        },
+        InputMode::FilteringTasks => match key {
+            // Leave the filter applied
+            KeyCode::Enter => InputMode::Normal,
+            KeyCode::Esc => {
+                app_state.task_filter.clear();
+                app_state.apply_task_filter();
+                InputMode::Normal
+            }
+            KeyCode::Up => {
+                app_state.tasks.previous();
+                InputMode::FilteringTasks
+            }
+            KeyCode::Down => {
+                app_state.tasks.next();
+                InputMode::FilteringTasks
+            }
+            KeyCode::Char(c) => {
+                app_state.task_filter.push(c);
+                app_state.apply_task_filter();
+                InputMode::FilteringTasks
+            }
+            KeyCode::Backspace => {
+                app_state.task_filter.pop();
+                app_state.apply_task_filter();
+                InputMode::FilteringTasks
+            }
+            _ => InputMode::FilteringTasks,
+        },
+        InputMode::ConfirmDelete(index) => match key {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                app_state.delete_task(index);
+                InputMode::Normal
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => InputMode::Normal,
+            _ => InputMode::ConfirmDelete(index),
+        },
+        InputMode::PickingProfile(index) => match key {
+            KeyCode::Char('j') | KeyCode::Down => {
+                InputMode::PickingProfile((index + 1) % app_state.profiles.len())
+            }
+            KeyCode::Char('k') | KeyCode::Up => InputMode::PickingProfile(
+                (index + app_state.profiles.len() - 1) % app_state.profiles.len(),
+            ),
+            KeyCode::Enter => {
+                let profile = app_state.profiles[index].clone();
+                app_state.switch_profile(config, profile);
+                InputMode::Normal
+            }
+            KeyCode::Esc => InputMode::Normal,
+            _ => InputMode::PickingProfile(index),
+        },
+        InputMode::ConfirmResume(saved) => match key {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                app_state.resume_saved(saved);
+                InputMode::Normal
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                app_state.discard_saved(saved);
+                InputMode::Normal
+            }
+            _ => InputMode::ConfirmResume(saved),
+        },
+        InputMode::ConfirmReload => match key {
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                app_state.reload_tasks();
+                InputMode::Normal
+            }
+            // Keeping the in-app changes means the next save overwrites the file
+            KeyCode::Char('k') | KeyCode::Char('K') | KeyCode::Esc => InputMode::Normal,
+            _ => InputMode::ConfirmReload,
+        },
+        InputMode::ConfirmQuit => match key {
+            KeyCode::Char('s') | KeyCode::Char('S') => {
+                if app_state.save_tasks() {
+                    app_state.quit();
+                }
+                InputMode::Normal
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                app_state.quit();
+                InputMode::Normal
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => InputMode::Normal,
+            _ => InputMode::ConfirmQuit,
+        },
+    };
+}
+
+/// Run a command received over the control socket.
+#[cfg(unix)]
+fn handle_control_command(
+    app_state: &mut AppState,
+    config: &Config,
+    command: &str,
+) -> Result<String, String> {
+    match command.parse::<ControlCommand>()? {
+        ControlCommand::Pause => app_state.pause_timer(),
+        ControlCommand::Resume => app_state.resume_timer(),
+        ControlCommand::Toggle => app_state.toggle_timer(),
+        ControlCommand::Reset => app_state.reset_timer(),
+        ControlCommand::Mode(mode) => app_state.switch_mode(config, mode),
+        ControlCommand::Select(index) => {
+            if index >= app_state.tasks.items.len() {
+                return Err(format!("there is no task {}", index + 1));
+            } else if !app_state.tasks.select(index) {
+                return Err(format!("task {} is hidden by the filter", index + 1));
+            }
+        }
+        ControlCommand::SelectNext => app_state.tasks.next(),
+        ControlCommand::SelectPrevious => app_state.tasks.previous(),
+        ControlCommand::Status => {
+            let status = Status {
+                mode: app_state.timer.pomodoro_mode,
+                status: app_state.timer.status,
+                time_remaining: app_state.timer.remaining_secs(),
+                overtime: app_state.timer.overtime().as_secs(),
+                mm_ss: app_state.timer.padded_mm_ss(),
+                hh_mm_ss: app_state.timer.padded_hh_mm_ss(),
+                percentage: app_state.timer.percentage(),
+                task: app_state.active_task_title(),
+            };
+
+            return serde_json::to_string(&status).map_err(|err| err.to_string());
+        }
+        ControlCommand::Activate(index) => match index.or(app_state.tasks.selected()) {
+            Some(index) if index < app_state.tasks.items.len() => {
+                app_state.active_task = Some(index)
+            }
+            Some(index) => return Err(format!("there is no task {}", index + 1)),
+            None => return Err(String::from("no task is selected")),
+        },
+        ControlCommand::Complete(index) => match index.or(app_state.tasks.selected()) {
+            Some(index) if index < app_state.tasks.items.len() => {
+                app_state.toggle_task_completed(index)
+            }
+            Some(index) => return Err(format!("there is no task {}", index + 1)),
+            None => return Err(String::from("no task is selected")),
+        },
+    }
+
+    Ok(String::from("ok"))
+}
 
-        last_tick = Instant::now();
+fn edit_task_form(form: &mut TaskForm, key: KeyCode) {
+    match key {
+        KeyCode::Char(c) => form.push(c),
+        KeyCode::Backspace => form.pop(),
+        KeyCode::Tab | KeyCode::Down => form.next_field(),
+        KeyCode::BackTab | KeyCode::Up => form.previous_field(),
+        _ => {}
     }
 }
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{pomodoro_mode::PomodoroMode, timer::Timer};
+
+/// A single step in a profile, e.g. a 50 minute pomodoro.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Period {
+    pub mode: PomodoroMode,
+    /// Length in minutes. Ignored for flowtime, and for breaks following flowtime, which earn
+    /// their length from the time spent focusing.
+    #[serde(default)]
+    pub duration: u64,
+    /// Shown in place of the mode's name.
+    pub label: Option<String>,
+}
+
+impl Period {
+    pub fn new(mode: PomodoroMode, duration: u64) -> Self {
+        Self {
+            mode,
+            duration,
+            label: None,
+        }
+    }
+
+    pub fn timer(&self) -> Timer {
+        match self.mode {
+            PomodoroMode::Flowtime => Timer::count_up(self.mode),
+            _ => Timer::new(Duration::from_secs(self.duration * 60), self.mode),
+        }
+    }
+}
+
+/// A named sequence of periods that the cycle runs through, e.g. 50/10/50/10/50/30.
+#[derive(Clone)]
+pub struct Profile {
+    pub name: String,
+    pub periods: Vec<Period>,
+}
+
+/// Tracks the position within the active profile.
+pub struct Cycle {
+    pub profile: Profile,
+    /// Index of the current period in the profile.
+    pub position: usize,
+}
+
+impl Cycle {
+    /// Start at the beginning of `profile`, which must have at least one period.
+    pub fn new(profile: Profile) -> Self {
+        Self {
+            profile,
+            position: 0,
+        }
+    }
+
+    pub fn current(&self) -> &Period {
+        &self.profile.periods[self.position]
+    }
+
+    fn next_position(&self) -> usize {
+        (self.position + 1) % self.profile.periods.len()
+    }
+
+    /// The period following the current one, without changing the cycle.
+    pub fn next(&self) -> &Period {
+        &self.profile.periods[self.next_position()]
+    }
+
+    /// Move on to the next period.
+    pub fn advance(&mut self) -> &Period {
+        self.position = self.next_position();
+        self.current()
+    }
+
+    /// Jump to the next period in the given mode, starting from the current one. Returns `None`
+    /// and leaves the cycle untouched if the profile has no such period.
+    pub fn jump_to(&mut self, mode: PomodoroMode) -> Option<&Period> {
+        let length = self.profile.periods.len();
+        let position = (0..length)
+            .map(|offset| (self.position + offset) % length)
+            .find(|&position| self.profile.periods[position].mode == mode)?;
+
+        self.position = position;
+        Some(self.current())
+    }
+
+    /// Number of the current pomodoro within the profile, e.g. `3/4`. Breaks report the
+    /// pomodoro that preceded them. `None` if the profile has fewer than two pomodoros.
+    pub fn progress(&self) -> Option<String> {
+        let is_pomodoro = |period: &&Period| period.mode == PomodoroMode::Pomodoro;
+
+        let total = self.profile.periods.iter().filter(is_pomodoro).count();
+        if total < 2 {
+            return None;
+        }
+
+        let position = self.profile.periods[..=self.position]
+            .iter()
+            .filter(is_pomodoro)
+            .count()
+            .max(1);
+
+        Some(format!("{}/{}", position, total))
+    }
+
+    /// The name of the current period, its label if it has one.
+    pub fn title(&self) -> String {
+        let period = self.current();
+
+        match &period.label {
+            Some(label) => label.clone(),
+            None => period.mode.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Four pomodoros with short breaks between them and a long break at the end.
+    fn classic() -> Cycle {
+        let mut periods = vec![];
+        for _ in 0..3 {
+            periods.push(Period::new(PomodoroMode::Pomodoro, 25));
+            periods.push(Period::new(PomodoroMode::ShortBreak, 5));
+        }
+        periods.push(Period::new(PomodoroMode::Pomodoro, 25));
+        periods.push(Period::new(PomodoroMode::LongBreak, 15));
+
+        Cycle::new(Profile {
+            name: String::from("classic"),
+            periods,
+        })
+    }
+
+    #[test]
+    fn advance_wraps_around_to_the_start() {
+        let mut cycle = classic();
+        cycle.position = 7;
+
+        assert!(cycle.next().mode == PomodoroMode::Pomodoro);
+        assert!(cycle.advance().mode == PomodoroMode::Pomodoro);
+        assert_eq!(cycle.position, 0);
+    }
+
+    #[test]
+    fn jump_to_finds_the_next_period_in_the_mode() {
+        let mut cycle = classic();
+        cycle.position = 2;
+
+        assert_eq!(
+            cycle
+                .jump_to(PomodoroMode::LongBreak)
+                .map(|period| period.duration),
+            Some(15)
+        );
+        assert_eq!(cycle.position, 7);
+
+        // The current period counts, and the search wraps around
+        cycle.jump_to(PomodoroMode::LongBreak);
+        assert_eq!(cycle.position, 7);
+        cycle.jump_to(PomodoroMode::ShortBreak);
+        assert_eq!(cycle.position, 1);
+    }
+
+    #[test]
+    fn jump_to_leaves_the_cycle_alone_without_the_mode() {
+        let mut cycle = classic();
+        cycle.position = 3;
+
+        assert!(cycle.jump_to(PomodoroMode::Flowtime).is_none());
+        assert_eq!(cycle.position, 3);
+    }
+
+    #[test]
+    fn progress_counts_pomodoros_and_breaks_report_the_one_before() {
+        let mut cycle = classic();
+        assert_eq!(cycle.progress().as_deref(), Some("1/4"));
+
+        cycle.position = 4;
+        assert_eq!(cycle.progress().as_deref(), Some("3/4"));
+        cycle.position = 5;
+        assert_eq!(cycle.progress().as_deref(), Some("3/4"));
+        cycle.position = 7;
+        assert_eq!(cycle.progress().as_deref(), Some("4/4"));
+    }
+
+    #[test]
+    fn progress_is_left_out_with_fewer_than_two_pomodoros() {
+        let cycle = Cycle::new(Profile {
+            name: String::from("single"),
+            periods: vec![
+                Period::new(PomodoroMode::Pomodoro, 50),
+                Period::new(PomodoroMode::LongBreak, 10),
+            ],
+        });
+
+        assert_eq!(cycle.progress(), None);
+    }
+
+    #[test]
+    fn title_prefers_the_period_label() {
+        let mut cycle = classic();
+        assert_eq!(cycle.title(), "Pomodoro");
+
+        cycle.profile.periods[0].label = Some(String::from("Deep work"));
+        assert_eq!(cycle.title(), "Deep work");
+    }
+}
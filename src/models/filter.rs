@@ -0,0 +1,142 @@
+use super::task::Task;
+
+/// One word of a filter query.
+enum Term {
+    /// Part of the title.
+    Text(String),
+    /// The start of one of the task's tags.
+    Tag(String),
+    /// The start of the task's project.
+    Project(String),
+    Completed(bool),
+}
+
+/// A query typed into the task list's filter bar. A task has to match every word of it:
+///
+/// - `#tag` matches tasks with a tag starting with `tag`
+/// - `@project` matches tasks in a project starting with `project`
+/// - `is:done` and `is:open` match completed and unfinished tasks
+/// - any other word matches tasks with it in their title
+///
+/// Matching ignores case.
+pub struct TaskFilter {
+    terms: Vec<Term>,
+}
+
+impl TaskFilter {
+    pub fn parse(query: &str) -> Self {
+        let terms = query
+            .split_whitespace()
+            .map(|word| {
+                let word = word.to_lowercase();
+
+                if let Some(tag) = word.strip_prefix('#') {
+                    Term::Tag(tag.to_string())
+                } else if let Some(project) = word.strip_prefix('@') {
+                    Term::Project(project.to_string())
+                } else if word == "is:done" {
+                    Term::Completed(true)
+                } else if word == "is:open" {
+                    Term::Completed(false)
+                } else {
+                    Term::Text(word)
+                }
+            })
+            .collect();
+
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Text(text) => task.title.to_lowercase().contains(text.as_str()),
+            Term::Tag(tag) => task
+                .tags
+                .iter()
+                .any(|task_tag| task_tag.to_lowercase().starts_with(tag.as_str())),
+            Term::Project(project) => task.project.as_ref().is_some_and(|task_project| {
+                task_project.to_lowercase().starts_with(project.as_str())
+            }),
+            Term::Completed(completed) => task.completed == *completed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(title: &str, project: Option<&str>, tags: &[&str], completed: bool) -> Task {
+        let mut task = Task::new(title.to_string(), 1);
+        task.project = project.map(str::to_string);
+        task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        task.completed = completed;
+        task
+    }
+
+    fn matches(query: &str, task: &Task) -> bool {
+        TaskFilter::parse(query).matches(task)
+    }
+
+    #[test]
+    fn text_matches_anywhere_in_the_title_ignoring_case() {
+        let task = task("Write Quarterly Report", None, &[], false);
+
+        assert!(matches("report", &task));
+        assert!(matches("TERLY", &task));
+        assert!(!matches("invoice", &task));
+    }
+
+    #[test]
+    fn tag_matches_the_start_of_any_tag() {
+        let task = task("Report", None, &["Writing", "q3"], false);
+
+        assert!(matches("#writ", &task));
+        assert!(matches("#Q3", &task));
+        assert!(!matches("#iting", &task));
+        assert!(!matches("#report", &task));
+    }
+
+    #[test]
+    fn project_matches_the_start_of_the_project() {
+        let with_project = task("Report", Some("Work"), &[], false);
+        let without_project = task("Report", None, &[], false);
+
+        assert!(matches("@wo", &with_project));
+        assert!(!matches("@ork", &with_project));
+        assert!(!matches("@work", &without_project));
+    }
+
+    #[test]
+    fn status_matches_completed_or_open_tasks() {
+        let done = task("Report", None, &[], true);
+        let open = task("Report", None, &[], false);
+
+        assert!(matches("is:done", &done));
+        assert!(!matches("is:done", &open));
+        assert!(matches("IS:OPEN", &open));
+        assert!(!matches("is:open", &done));
+    }
+
+    #[test]
+    fn every_word_has_to_match() {
+        let task = task("Write report", Some("Work"), &["writing"], false);
+
+        assert!(matches("report @work #writing is:open", &task));
+        assert!(!matches("report @home", &task));
+        assert!(!matches("report is:done", &task));
+    }
+
+    #[test]
+    fn blank_query_is_empty_and_matches_everything() {
+        let filter = TaskFilter::parse("   ");
+
+        assert!(filter.is_empty());
+        assert!(filter.matches(&task("Report", None, &[], true)));
+        assert!(!TaskFilter::parse("#").is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths::xdg_dir;
+
+use super::{pomodoro_mode::PomodoroMode, task::Task, timer::Timer};
+
+/// How a timer run came to an end.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionOutcome {
+    /// The timer ran all the way down.
+    Finished,
+    /// The user switched to another mode before the timer finished.
+    Skipped,
+    /// The user restarted the timer.
+    Reset,
+    /// The application was closed while the timer was running.
+    Quit,
+}
+
+/// A single timer run. Timestamps are seconds since the Unix epoch and durations are in
+/// seconds. Count-up timers have a planned duration of zero.
+#[derive(Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub mode: PomodoroMode,
+    pub planned_duration: u64,
+    pub actual_duration: u64,
+    pub paused_duration: u64,
+    /// Time spent running past the planned duration, included in `actual_duration`.
+    #[serde(default)]
+    pub overtime_duration: u64,
+    pub outcome: SessionOutcome,
+    pub task: Option<String>,
+    /// The task's project and tags at the time, so the history can be grouped by them.
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl SessionRecord {
+    /// Create a record of the given timer. Returns `None` if the timer was never started.
+    pub fn from_timer(timer: &Timer, outcome: SessionOutcome, task: Option<&Task>) -> Option<Self> {
+        let started_at = timer.started_at()?;
+
+        Some(Self {
+            started_at: unix_seconds(started_at),
+            ended_at: unix_seconds(SystemTime::now()),
+            mode: timer.pomodoro_mode,
+            planned_duration: timer.total_time.unwrap_or_default().as_secs(),
+            actual_duration: timer.elapsed().as_secs(),
+            paused_duration: timer.paused_time().as_secs(),
+            overtime_duration: timer.overtime().as_secs(),
+            outcome,
+            task: task.map(|task| task.title.clone()),
+            project: task.and_then(|task| task.project.clone()),
+            tags: task.map(|task| task.tags.clone()).unwrap_or_default(),
+        })
+    }
+}
+
+pub fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+/// An append-only log of timer runs, stored one JSON record per line.
+pub struct History {
+    pub path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The default history location, `$XDG_DATA_HOME/pomodoro/history`, falling back to
+    /// `~/.local/share/pomodoro/history`.
+    pub fn default_path() -> PathBuf {
+        xdg_dir("XDG_DATA_HOME", ".local/share")
+            .join("pomodoro")
+            .join("history")
+    }
+
+    pub fn append(&self, record: &SessionRecord) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// Read every record in the history. A missing file is treated as an empty history.
+    ///
+    /// Lines that aren't a valid record are skipped, as a crash part way through an append, or
+    /// two instances appending at once, can leave a broken line behind.
+    pub fn load(&self) -> io::Result<Vec<SessionRecord>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_skips_broken_lines() {
+        let path = std::env::temp_dir().join(format!("pomodoro-history-{}", std::process::id()));
+        let history = History::new(path.clone());
+        let mut timer = Timer::new(Duration::from_secs(60), PomodoroMode::Pomodoro);
+        timer.unpause();
+        let record = SessionRecord::from_timer(&timer, SessionOutcome::Reset, None).unwrap();
+
+        history.append(&record).unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"started_at\":17922\n\nnot json\n")
+            .unwrap();
+        history.append(&record).unwrap();
+        let records = history.load().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert!(records
+            .iter()
+            .all(|record| record.outcome == SessionOutcome::Reset));
+    }
+}
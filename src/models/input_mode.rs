@@ -0,0 +1,151 @@
+use super::{saved_state::SavedState, task::Task};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum InputField {
+    Title,
+    PomodorosExpected,
+    Project,
+    Tags,
+}
+
+/// Text being entered into the task popup.
+pub struct TaskForm {
+    pub title: String,
+    pub pomodoros_expected: String,
+    pub project: String,
+    /// Comma separated.
+    pub tags: String,
+    pub field: InputField,
+}
+
+impl TaskForm {
+    pub fn new() -> Self {
+        Self {
+            title: String::new(),
+            pomodoros_expected: String::new(),
+            project: String::new(),
+            tags: String::new(),
+            field: InputField::Title,
+        }
+    }
+
+    /// Create a form pre-filled with an existing task's values.
+    pub fn from_task(task: &Task) -> Self {
+        Self {
+            title: task.title.clone(),
+            pomodoros_expected: task.pomodoros_expected.to_string(),
+            ..Self::for_subtask(task)
+        }
+    }
+
+    /// Create an empty form for a subtask of `parent`, which starts out in the same project
+    /// and with the same tags.
+    pub fn for_subtask(parent: &Task) -> Self {
+        Self {
+            project: parent.project.clone().unwrap_or_default(),
+            tags: parent.tags.join(", "),
+            ..Self::new()
+        }
+    }
+
+    /// Add a character to the currently focused field.
+    /// The pomodoros field only accepts digits.
+    pub fn push(&mut self, c: char) {
+        match self.field {
+            InputField::Title => self.title.push(c),
+            InputField::PomodorosExpected => {
+                if c.is_ascii_digit() {
+                    self.pomodoros_expected.push(c)
+                }
+            }
+            InputField::Project => self.project.push(c),
+            InputField::Tags => self.tags.push(c),
+        }
+    }
+
+    /// Remove the last character from the currently focused field.
+    pub fn pop(&mut self) {
+        match self.field {
+            InputField::Title => self.title.pop(),
+            InputField::PomodorosExpected => self.pomodoros_expected.pop(),
+            InputField::Project => self.project.pop(),
+            InputField::Tags => self.tags.pop(),
+        };
+    }
+
+    pub fn next_field(&mut self) {
+        self.field = match self.field {
+            InputField::Title => InputField::PomodorosExpected,
+            InputField::PomodorosExpected => InputField::Project,
+            InputField::Project => InputField::Tags,
+            InputField::Tags => InputField::Title,
+        };
+    }
+
+    pub fn previous_field(&mut self) {
+        self.field = match self.field {
+            InputField::Title => InputField::Tags,
+            InputField::PomodorosExpected => InputField::Title,
+            InputField::Project => InputField::PomodorosExpected,
+            InputField::Tags => InputField::Project,
+        };
+    }
+
+    /// Whether the form holds enough to be turned into a task.
+    pub fn is_valid(&self) -> bool {
+        !self.title.trim().is_empty()
+    }
+
+    pub fn pomodoros_expected(&self) -> u16 {
+        self.pomodoros_expected.parse().unwrap_or_default()
+    }
+
+    /// Copy the form's values onto `task`.
+    pub fn update(&self, task: &mut Task) {
+        let project = self.project.trim();
+
+        task.title = self.title.trim().to_string();
+        task.pomodoros_expected = self.pomodoros_expected();
+        task.project = if project.is_empty() {
+            None
+        } else {
+            Some(project.to_string())
+        };
+        task.tags = Task::parse_tags(&self.tags);
+    }
+
+    /// Create a task from the form's values.
+    pub fn task(&self) -> Task {
+        let mut task = Task::new(String::new(), 0);
+        self.update(&mut task);
+
+        task
+    }
+}
+
+impl Default for TaskForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub enum InputMode {
+    Normal,
+    AddingTask(TaskForm),
+    /// Adding a subtask to the task at the given index.
+    AddingSubtask(usize, TaskForm),
+    /// Editing the task at the given index.
+    EditingTask(usize, TaskForm),
+    /// Typing into the task list's filter bar.
+    FilteringTasks,
+    /// Waiting for the user to confirm the deletion of the task at the given index.
+    ConfirmDelete(usize),
+    /// Choosing a profile, with the given profile highlighted.
+    PickingProfile(usize),
+    /// Asking whether to resume the timer that was running when the application was closed.
+    ConfirmResume(SavedState),
+    /// Asking whether to reload the task file after it changed on disk, losing unsaved changes.
+    ConfirmReload,
+    /// Asking what to do with unsaved task changes before quitting.
+    ConfirmQuit,
+}
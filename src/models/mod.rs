@@ -1,5 +1,11 @@
+pub mod cycle;
+pub mod filter;
+pub mod history;
+pub mod input_mode;
 pub mod pomodoro_mode;
+pub mod saved_state;
+pub mod stateful_list;
+pub mod stats;
 pub mod study_mode;
 pub mod task;
 pub mod timer;
-pub mod stateful_list;
@@ -1,9 +1,15 @@
 use core::fmt;
 
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PomodoroMode {
     Pomodoro,
     ShortBreak,
     LongBreak,
+    /// Counts up from zero until stopped, earning a break proportional to the time spent.
+    Flowtime,
 }
 
 impl fmt::Display for PomodoroMode {
@@ -12,8 +18,36 @@ impl fmt::Display for PomodoroMode {
             PomodoroMode::Pomodoro => "Pomodoro",
             PomodoroMode::ShortBreak => "Short Break",
             PomodoroMode::LongBreak => "Long Break",
+            PomodoroMode::Flowtime => "Flowtime",
         };
 
         write!(f, "{}", text)
     }
 }
+
+impl PomodoroMode {
+    pub const ALL: [PomodoroMode; 4] = [
+        PomodoroMode::Pomodoro,
+        PomodoroMode::ShortBreak,
+        PomodoroMode::LongBreak,
+        PomodoroMode::Flowtime,
+    ];
+
+    /// Identifier used in config files, the control socket and hook environment variables.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PomodoroMode::Pomodoro => "pomodoro",
+            PomodoroMode::ShortBreak => "short_break",
+            PomodoroMode::LongBreak => "long_break",
+            PomodoroMode::Flowtime => "flowtime",
+        }
+    }
+
+    /// Whether the mode is for working rather than taking a break.
+    pub fn is_focus(&self) -> bool {
+        match self {
+            PomodoroMode::Pomodoro | PomodoroMode::Flowtime => true,
+            PomodoroMode::ShortBreak | PomodoroMode::LongBreak => false,
+        }
+    }
+}
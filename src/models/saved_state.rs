@@ -0,0 +1,73 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths::{write_atomic, xdg_dir};
+
+use super::timer::TimerSnapshot;
+
+/// The in-progress timer and where it sits in the cycle, saved so it can be resumed after the
+/// application is closed.
+#[derive(Serialize, Deserialize)]
+pub struct SavedState {
+    pub saved_at: SystemTime,
+    pub timer: TimerSnapshot,
+    pub profile: String,
+    /// Index of the current period in the profile.
+    pub position: usize,
+    /// Title of the active task.
+    pub task: Option<String>,
+    /// `Task::path` of the active task, so a subtask isn't mistaken for another with the same
+    /// title. Missing from state saved by older versions.
+    #[serde(default)]
+    pub task_path: Option<Vec<String>>,
+}
+
+impl SavedState {
+    /// Wall-clock time since the state was saved.
+    pub fn time_away(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.saved_at)
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+/// The file the state is saved to, written as a single JSON document.
+pub struct StateFile {
+    pub path: PathBuf,
+}
+
+impl StateFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The default state location, `$XDG_STATE_HOME/pomodoro/state.json`, falling back to
+    /// `~/.local/state/pomodoro/state.json`.
+    pub fn default_path() -> PathBuf {
+        xdg_dir("XDG_STATE_HOME", ".local/state")
+            .join("pomodoro")
+            .join("state.json")
+    }
+
+    pub fn save(&self, state: &SavedState) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        write_atomic(&self.path, &serde_json::to_string(state)?)
+    }
+
+    /// Read the saved state, `None` if there isn't one.
+    pub fn load(&self) -> io::Result<Option<SavedState>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
@@ -1,69 +1,260 @@
+use std::ops::Range;
+
 use tui::widgets::ListState;
 
+/// An item that can be nested under another. Items are kept in depth-first order, so the
+/// children of an item are the items straight after it with a greater depth.
+pub trait Nested {
+    fn depth(&self) -> usize;
+    fn collapsed(&self) -> bool;
+    fn set_collapsed(&mut self, collapsed: bool);
+}
+
+/// The item at `index` along with everything nested under it.
+pub fn subtree<T: Nested>(items: &[T], index: usize) -> Range<usize> {
+    let depth = items[index].depth();
+    let end = items[index + 1..]
+        .iter()
+        .position(|item| item.depth() <= depth)
+        .map_or(items.len(), |offset| index + 1 + offset);
+
+    index..end
+}
+
+/// The item that the item at `index` is nested under, if any.
+pub fn parent<T: Nested>(items: &[T], index: usize) -> Option<usize> {
+    let depth = items[index].depth();
+
+    items[..index].iter().rposition(|item| item.depth() < depth)
+}
+
+/// Decides whether an item is shown, see `StatefulList::set_filter`.
+pub type Filter<T> = Box<dyn Fn(&T) -> bool>;
+
 pub struct StatefulList<T> {
+    /// The selected row among the visible items, as drawn.
     pub state: ListState,
     pub items: Vec<T>,
+    /// Index of the selected item in `items`.
+    selected: Option<usize>,
+    /// Hides the items it doesn't match, unless something nested under them matches.
+    filter: Option<Filter<T>>,
 }
 
-impl<T> StatefulList<T> {
+impl<T: Nested> StatefulList<T> {
     /// Create a StatefulList with the items passed in.
     pub fn with_items(items: Vec<T>) -> Self {
         Self {
             state: ListState::default(),
             items,
+            selected: None,
+            filter: None,
         }
     }
 
-    /// Move the internally selected item forward
-    pub fn next(&mut self) {
-        if !self.items.is_empty() {
-            let i = match self.state.selected() {
-                Some(i) => {
-                    if i >= self.items.len() - 1 {
-                        i
-                    } else {
-                        i + 1
-                    }
-                }
-                None => 0,
-            };
+    /// Swap in a new set of items, selecting `selected` among them. Unlike starting a new list
+    /// this keeps the list scrolled where it was.
+    pub fn replace_items(&mut self, items: Vec<T>, selected: Option<usize>) {
+        self.items = items;
+        self.selected = None;
+
+        if let Some(selected) = selected {
+            self.select(selected);
+        }
+        self.refresh();
+    }
+
+    pub fn subtree(&self, index: usize) -> Range<usize> {
+        subtree(&self.items, index)
+    }
+
+    pub fn has_children(&self, index: usize) -> bool {
+        self.subtree(index).len() > 1
+    }
+
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        parent(&self.items, index)
+    }
+
+    /// The items directly nested under the item at `index`.
+    pub fn children(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let depth = self.items[index].depth();
+
+        self.subtree(index)
+            .skip(1)
+            .filter(move |&child| self.items[child].depth() == depth + 1)
+    }
+
+    /// Only show the items `filter` matches, along with the items they're nested under.
+    pub fn set_filter(&mut self, filter: Option<Filter<T>>) {
+        self.filter = filter;
+        self.refresh();
+    }
+
+    /// Check the selection is still shown after the items have changed, as a change can mean
+    /// the filter no longer matches it. A hidden item's selection moves to the next item shown,
+    /// or the last one if there's nothing after it.
+    pub fn refresh(&mut self) {
+        let visible = self.visible();
 
-            self.state.select(Some(i));
+        if !self
+            .selected
+            .is_some_and(|selected| visible.contains(&selected))
+        {
+            let selected = self.selected.unwrap_or(0);
+
+            self.selected = visible
+                .iter()
+                .find(|&&index| index >= selected)
+                .or(visible.last())
+                .copied();
         }
+        self.update_state();
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.filter.is_some()
     }
 
-    /// Move the internally selected item backwards.
+    /// Whether the filter, if any, matches the item at `index` or anything nested under it.
+    fn passes_filter(&self, index: usize) -> bool {
+        match &self.filter {
+            Some(filter) => self.items[self.subtree(index)].iter().any(filter),
+            None => true,
+        }
+    }
+
+    /// Indices of the items that aren't hidden by a collapsed parent or the filter, in order.
+    pub fn visible(&self) -> Vec<usize> {
+        let mut visible = vec![];
+        let mut collapsed_depth = None;
+
+        for (index, item) in self.items.iter().enumerate() {
+            match collapsed_depth {
+                Some(depth) if item.depth() > depth => continue,
+                _ => collapsed_depth = None,
+            }
+
+            // Nothing nested under a filtered out item can match either
+            if !self.passes_filter(index) {
+                collapsed_depth = Some(item.depth());
+                continue;
+            }
+
+            visible.push(index);
+            if item.collapsed() {
+                collapsed_depth = Some(item.depth());
+            }
+        }
+
+        visible
+    }
+
+    /// Point the drawn selection at the selected item's row.
+    fn update_state(&mut self) {
+        let row = self
+            .selected
+            .and_then(|selected| self.visible().iter().position(|&index| index == selected));
+
+        self.state.select(row);
+    }
+
+    /// Move the internally selected item forward, skipping over collapsed items.
+    pub fn next(&mut self) {
+        let visible = self.visible();
+        let row = match self.state.selected() {
+            Some(row) => (row + 1).min(visible.len().saturating_sub(1)),
+            None => 0,
+        };
+
+        self.selected = visible.get(row).copied();
+        self.update_state();
+    }
+
+    /// Move the internally selected item backwards, skipping over collapsed items.
     pub fn previous(&mut self) {
-        if !self.items.is_empty() {
-            let i = match self.state.selected() {
-                Some(i) => {
-                    if i == 0 {
-                        i
-                    } else {
-                        i - 1
-                    }
-                }
-                None => 0,
-            };
+        let visible = self.visible();
+        let row = self.state.selected().map_or(0, |row| row.saturating_sub(1));
+
+        self.selected = visible.get(row).copied();
+        self.update_state();
+    }
+
+    /// Collapse the selected item, or select its parent if there's nothing to collapse.
+    pub fn collapse(&mut self) {
+        if let Some(selected) = self.selected {
+            if self.has_children(selected) && !self.items[selected].collapsed() {
+                self.items[selected].set_collapsed(true);
+            } else if let Some(parent) = self.parent(selected) {
+                self.selected = Some(parent);
+            }
+            self.update_state();
+        }
+    }
+
+    /// Expand the selected item, or select its first child shown if it's already expanded.
+    pub fn expand(&mut self) {
+        if let Some(selected) = self
+            .selected
+            .filter(|&selected| self.has_children(selected))
+        {
+            if self.items[selected].collapsed() {
+                self.items[selected].set_collapsed(false);
+            } else {
+                let subtree = self.subtree(selected);
 
-            self.state.select(Some(i))
+                if let Some(child) = self
+                    .visible()
+                    .into_iter()
+                    .find(|&index| index > selected && subtree.contains(&index))
+                {
+                    self.selected = Some(child);
+                }
+            }
+            self.update_state();
         }
     }
 
-    /// Delete an item by its index
-    pub fn delete(&mut self, index: usize) {
-        if !self.items.is_empty() {
-            self.items.remove(index);
+    /// Delete an item along with everything nested under it, returning the indices that were
+    /// removed.
+    pub fn delete(&mut self, index: usize) -> Range<usize> {
+        let removed = self.subtree(index);
+        let parent = self.parent(index);
+        self.items.drain(removed.clone());
 
-            if !self.items.is_empty() && index > self.items.len() - 1 {
-                self.previous();
+        // A parent left without children has nothing to collapse
+        if let Some(parent) = parent {
+            if !self.has_children(parent) {
+                self.items[parent].set_collapsed(false);
             }
         }
+
+        self.selected = match self.selected {
+            Some(selected) if selected >= removed.end => Some(selected - removed.len()),
+            Some(selected) if selected >= removed.start => {
+                // Move to what took its place, or the row above if it was the last one
+                let visible = self.visible();
+                visible
+                    .iter()
+                    .find(|&&index| index >= removed.start)
+                    .or(visible.last())
+                    .copied()
+            }
+            selected => selected,
+        };
+        self.update_state();
+
+        removed
     }
 
     /// Insert an item at the specified index position
     pub fn insert(&mut self, new_item: T, index: usize) {
         self.items.insert(index, new_item);
+
+        if let Some(selected) = self.selected.filter(|&selected| selected >= index) {
+            self.selected = Some(selected + 1);
+        }
+        self.update_state();
     }
 
     /// push an item to the end of the list.
@@ -71,8 +262,140 @@ impl<T> StatefulList<T> {
         self.items.push(new_item);
     }
 
+    /// Select the item at `index`, expanding its parents so it can be seen. Returns false if
+    /// there is no such item or the filter hides it.
+    pub fn select(&mut self, index: usize) -> bool {
+        if index >= self.items.len() || !self.passes_filter(index) {
+            return false;
+        }
+
+        let mut child = index;
+        while let Some(parent) = self.parent(child) {
+            self.items[parent].set_collapsed(false);
+            child = parent;
+        }
+
+        self.selected = Some(index);
+        self.update_state();
+        true
+    }
+
     /// Return the current selected item.
-    pub fn selected(&mut self) -> Option<usize> {
-        self.state.selected()
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item {
+        depth: usize,
+        collapsed: bool,
+        done: bool,
+    }
+
+    impl Nested for Item {
+        fn depth(&self) -> usize {
+            self.depth
+        }
+
+        fn collapsed(&self) -> bool {
+            self.collapsed
+        }
+
+        fn set_collapsed(&mut self, collapsed: bool) {
+            self.collapsed = collapsed;
+        }
+    }
+
+    fn list(depths: &[usize]) -> StatefulList<Item> {
+        StatefulList::with_items(
+            depths
+                .iter()
+                .map(|&depth| Item {
+                    depth,
+                    collapsed: false,
+                    done: false,
+                })
+                .collect(),
+        )
+    }
+
+    fn open_only() -> Option<Filter<Item>> {
+        Some(Box::new(|item: &Item| !item.done))
+    }
+
+    #[test]
+    fn refresh_moves_selection_off_an_item_the_filter_now_hides() {
+        let mut list = list(&[0, 0, 0]);
+        list.set_filter(open_only());
+        list.select(0);
+
+        list.items[0].done = true;
+        list.refresh();
+
+        assert_eq!(list.selected(), Some(1));
+        assert_eq!(list.state.selected(), Some(0));
+    }
+
+    #[test]
+    fn refresh_falls_back_to_the_last_item_shown() {
+        let mut list = list(&[0, 0, 0]);
+        list.set_filter(open_only());
+        list.select(2);
+
+        list.items[2].done = true;
+        list.refresh();
+
+        assert_eq!(list.selected(), Some(1));
+        assert_eq!(list.state.selected(), Some(1));
+    }
+
+    #[test]
+    fn select_refuses_items_the_filter_hides() {
+        let mut list = list(&[0, 0]);
+        list.items[1].done = true;
+        list.set_filter(open_only());
+
+        assert!(!list.select(1));
+        assert_eq!(list.selected(), Some(0));
+    }
+
+    #[test]
+    fn filter_keeps_parents_of_matching_items() {
+        let mut list = list(&[0, 1, 0]);
+        list.items[0].done = true;
+        list.items[2].done = true;
+        list.set_filter(open_only());
+
+        assert_eq!(list.visible(), vec![0, 1]);
+    }
+
+    #[test]
+    fn expand_skips_children_the_filter_hides() {
+        let mut list = list(&[0, 1, 1]);
+        list.items[1].done = true;
+        list.set_filter(open_only());
+        list.select(0);
+
+        list.expand();
+
+        assert_eq!(list.selected(), Some(2));
+        assert_eq!(list.state.selected(), Some(1));
+    }
+
+    #[test]
+    fn expand_stays_put_when_the_filter_hides_every_child() {
+        let mut list = list(&[0, 1, 0]);
+        list.items[1].done = true;
+        list.set_filter(open_only());
+        list.select(0);
+
+        list.expand();
+
+        assert_eq!(list.selected(), Some(0));
+        assert_eq!(list.state.selected(), Some(0));
     }
 }
@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Days, Local, NaiveDate};
+
+use super::{
+    history::{SessionOutcome, SessionRecord},
+    pomodoro_mode::PomodoroMode,
+    stateful_list::subtree,
+    task::Task,
+};
+
+/// Number of days shown in the daily charts.
+pub const STATS_DAYS: u64 = 14;
+
+pub struct DayStats {
+    pub date: NaiveDate,
+    pub pomodoros: u64,
+    pub focus_minutes: u64,
+}
+
+pub struct TaskStats {
+    pub title: String,
+    pub pomodoros_expected: u16,
+    pub pomodoros_completed: u16,
+    /// Finished pomodoros recorded against the task in the session history.
+    pub pomodoros_logged: u64,
+}
+
+/// Figures for the tasks and sessions in a project, or with a tag.
+pub struct GroupStats {
+    /// `None` for those without a project, or without tags.
+    pub name: Option<String>,
+    pub pomodoros_expected: u64,
+    pub pomodoros_completed: u64,
+    pub pomodoros_logged: u64,
+    pub focus_minutes: u64,
+}
+
+/// What the table on the statistics view is broken down by.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatsGrouping {
+    #[default]
+    Task,
+    Project,
+    Tag,
+}
+
+impl StatsGrouping {
+    pub fn next(self) -> Self {
+        match self {
+            StatsGrouping::Task => StatsGrouping::Project,
+            StatsGrouping::Project => StatsGrouping::Tag,
+            StatsGrouping::Tag => StatsGrouping::Task,
+        }
+    }
+}
+
+/// Figures shown on the statistics view, worked out from the session history.
+#[derive(Default)]
+pub struct Stats {
+    /// The last `STATS_DAYS` days, oldest first.
+    pub days: Vec<DayStats>,
+    /// Consecutive days, up to today, with at least one finished pomodoro.
+    pub current_streak: u64,
+    pub tasks: Vec<TaskStats>,
+    pub projects: Vec<GroupStats>,
+    pub tags: Vec<GroupStats>,
+}
+
+fn local_date(timestamp: u64) -> Option<NaiveDate> {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|time| time.with_timezone(&Local).date_naive())
+}
+
+fn is_finished_pomodoro(record: &SessionRecord) -> bool {
+    record.mode == PomodoroMode::Pomodoro && record.outcome == SessionOutcome::Finished
+}
+
+/// The project and tags a session was logged with. Sessions logged before they were recorded,
+/// or whose task had none at the time, take them from the task as it is now.
+fn record_labels<'a>(
+    record: &'a SessionRecord,
+    tasks: &'a [Task],
+) -> (Option<&'a str>, &'a [String]) {
+    if record.project.is_none() && record.tags.is_empty() {
+        if let Some(task) = record
+            .task
+            .as_ref()
+            .and_then(|title| tasks.iter().find(|task| &task.title == title))
+        {
+            return (task.project.as_deref(), &task.tags);
+        }
+    }
+
+    (record.project.as_deref(), &record.tags)
+}
+
+/// Total up the tasks and sessions under each of the names `labels` picks out of a project and
+/// tags. Those it picks none for are totalled last, under no name.
+fn group_stats<'a>(
+    records: &'a [SessionRecord],
+    tasks: &'a [Task],
+    labels: impl Fn(Option<&'a str>, &'a [String]) -> Vec<&'a str>,
+) -> Vec<GroupStats> {
+    // Focus time is summed in seconds and only rounded down to minutes at the end
+    let mut groups: BTreeMap<Option<&str>, (GroupStats, u64)> = BTreeMap::new();
+    let mut add = |project, tags, update: &dyn Fn(&mut (GroupStats, u64))| {
+        let names = labels(project, tags);
+        let names = if names.is_empty() {
+            vec![None]
+        } else {
+            names.into_iter().map(Some).collect()
+        };
+
+        for name in names {
+            update(groups.entry(name).or_insert_with(|| {
+                (
+                    GroupStats {
+                        name: name.map(String::from),
+                        pomodoros_expected: 0,
+                        pomodoros_completed: 0,
+                        pomodoros_logged: 0,
+                        focus_minutes: 0,
+                    },
+                    0,
+                )
+            }));
+        }
+    };
+
+    // Each task counts its own pomodoros, as its subtasks are counted separately
+    for task in tasks {
+        add(task.project.as_deref(), &task.tags, &|(group, _)| {
+            group.pomodoros_expected += task.pomodoros_expected as u64;
+            group.pomodoros_completed += task.pomodoros_completed as u64;
+        });
+    }
+
+    for record in records.iter().filter(|record| record.mode.is_focus()) {
+        let (project, tags) = record_labels(record, tasks);
+
+        add(project, tags, &|(group, focus_seconds)| {
+            if is_finished_pomodoro(record) {
+                group.pomodoros_logged += 1;
+            }
+            *focus_seconds += record.actual_duration;
+        });
+    }
+
+    let mut groups: Vec<GroupStats> = groups
+        .into_values()
+        .map(|(group, focus_seconds)| GroupStats {
+            focus_minutes: focus_seconds / 60,
+            ..group
+        })
+        .collect();
+
+    // `None` sorts first
+    if groups.first().is_some_and(|group| group.name.is_none()) {
+        groups.rotate_left(1);
+    }
+
+    groups
+}
+
+impl Stats {
+    pub fn new(records: &[SessionRecord], tasks: &[Task], today: NaiveDate) -> Self {
+        let days = (0..STATS_DAYS)
+            .rev()
+            .filter_map(|offset| today.checked_sub_days(Days::new(offset)))
+            .map(|date| {
+                let day_records = records
+                    .iter()
+                    .filter(|record| local_date(record.started_at) == Some(date));
+
+                DayStats {
+                    date,
+                    pomodoros: day_records
+                        .clone()
+                        .filter(|record| is_finished_pomodoro(record))
+                        .count() as u64,
+                    focus_minutes: day_records
+                        .filter(|record| record.mode.is_focus())
+                        .map(|record| record.actual_duration)
+                        .sum::<u64>()
+                        / 60,
+                }
+            })
+            .collect();
+
+        // Parents are credited with their subtasks' pomodoros too
+        let task_stats = tasks
+            .iter()
+            .enumerate()
+            .map(|(index, task)| {
+                let (pomodoros_completed, pomodoros_expected) = Task::pomodoros(tasks, index);
+                let titles: Vec<&str> = tasks[subtree(tasks, index)]
+                    .iter()
+                    .map(|task| task.title.as_str())
+                    .collect();
+
+                TaskStats {
+                    title: format!("{}{}", "  ".repeat(task.depth), task.title),
+                    pomodoros_expected,
+                    pomodoros_completed,
+                    pomodoros_logged: records
+                        .iter()
+                        .filter(|record| {
+                            is_finished_pomodoro(record)
+                                && record
+                                    .task
+                                    .as_deref()
+                                    .is_some_and(|title| titles.contains(&title))
+                        })
+                        .count() as u64,
+                }
+            })
+            .collect();
+
+        Self {
+            days,
+            current_streak: current_streak(records, today),
+            tasks: task_stats,
+            projects: group_stats(records, tasks, |project, _| project.into_iter().collect()),
+            tags: group_stats(records, tasks, |_, tags| {
+                tags.iter().map(String::as_str).collect()
+            }),
+        }
+    }
+}
+
+/// Count the consecutive days with a finished pomodoro. A streak isn't broken until a whole
+/// day passes without one, so today not having any yet still counts from yesterday.
+fn current_streak(records: &[SessionRecord], today: NaiveDate) -> u64 {
+    let active_days = records
+        .iter()
+        .filter(|record| is_finished_pomodoro(record))
+        .filter_map(|record| local_date(record.started_at))
+        .collect::<HashSet<NaiveDate>>();
+
+    let mut date = if active_days.contains(&today) {
+        today
+    } else {
+        match today.pred_opt() {
+            Some(yesterday) => yesterday,
+            None => return 0,
+        }
+    };
+
+    let mut streak = 0;
+    while active_days.contains(&date) {
+        streak += 1;
+
+        date = match date.pred_opt() {
+            Some(previous) => previous,
+            None => break,
+        };
+    }
+
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    /// A session started at noon local time on `date`.
+    fn record(date: NaiveDate, mode: PomodoroMode, outcome: SessionOutcome) -> SessionRecord {
+        let started_at = date
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .timestamp() as u64;
+
+        SessionRecord {
+            started_at,
+            ended_at: started_at + 25 * 60,
+            mode,
+            planned_duration: 25 * 60,
+            actual_duration: 25 * 60,
+            paused_duration: 0,
+            overtime_duration: 0,
+            outcome,
+            task: None,
+            project: None,
+            tags: vec![],
+        }
+    }
+
+    fn finished_pomodoro(date: NaiveDate) -> SessionRecord {
+        record(date, PomodoroMode::Pomodoro, SessionOutcome::Finished)
+    }
+
+    #[test]
+    fn streak_counts_consecutive_days_up_to_today() {
+        let records = [
+            finished_pomodoro(date(10)),
+            finished_pomodoro(date(12)),
+            finished_pomodoro(date(13)),
+            finished_pomodoro(date(14)),
+        ];
+
+        assert_eq!(current_streak(&records, date(14)), 3);
+    }
+
+    #[test]
+    fn streak_survives_a_day_with_no_pomodoro_yet() {
+        let records = [finished_pomodoro(date(12)), finished_pomodoro(date(13))];
+
+        assert_eq!(current_streak(&records, date(14)), 2);
+        assert_eq!(current_streak(&records, date(15)), 0);
+    }
+
+    #[test]
+    fn streak_only_counts_finished_pomodoros() {
+        let records = [
+            finished_pomodoro(date(12)),
+            record(date(13), PomodoroMode::Pomodoro, SessionOutcome::Skipped),
+            record(date(14), PomodoroMode::ShortBreak, SessionOutcome::Finished),
+        ];
+
+        assert_eq!(current_streak(&records, date(14)), 0);
+    }
+
+    #[test]
+    fn days_cover_the_last_two_weeks_oldest_first() {
+        let records = [
+            finished_pomodoro(date(14)),
+            finished_pomodoro(date(14)),
+            record(date(14), PomodoroMode::Pomodoro, SessionOutcome::Reset),
+            finished_pomodoro(date(1)),
+        ];
+        let stats = Stats::new(&records, &[], date(14));
+
+        assert_eq!(stats.days.len(), STATS_DAYS as usize);
+        assert_eq!(stats.days[0].date, date(1));
+        assert_eq!(stats.days[0].pomodoros, 1);
+        let today = stats.days.last().unwrap();
+        assert_eq!(today.date, date(14));
+        assert_eq!(today.pomodoros, 2);
+        assert_eq!(today.focus_minutes, 75);
+    }
+
+    fn task(title: &str, project: Option<&str>, tags: &[&str]) -> Task {
+        let mut task = Task::new(title.to_string(), 2);
+        task.project = project.map(str::to_string);
+        task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        task
+    }
+
+    fn names(groups: &[GroupStats]) -> Vec<Option<&str>> {
+        groups.iter().map(|group| group.name.as_deref()).collect()
+    }
+
+    #[test]
+    fn groups_are_sorted_with_the_ungrouped_row_last() {
+        let tasks = [
+            task("Read", None, &[]),
+            task("Report", Some("Work"), &["writing"]),
+            task("Blog", Some("Home"), &["writing", "web"]),
+        ];
+        let stats = Stats::new(&[], &tasks, date(14));
+
+        assert_eq!(
+            names(&stats.projects),
+            vec![Some("Home"), Some("Work"), None]
+        );
+        assert_eq!(names(&stats.tags), vec![Some("web"), Some("writing"), None]);
+        assert_eq!(stats.tags[1].pomodoros_expected, 4);
+    }
+
+    #[test]
+    fn groups_total_logged_sessions() {
+        let tasks = [task("Report", Some("Work"), &[])];
+        let mut logged = finished_pomodoro(date(14));
+        logged.task = Some(String::from("Report"));
+        let mut tagged = finished_pomodoro(date(14));
+        tagged.project = Some(String::from("Home"));
+        let mut skipped = record(date(14), PomodoroMode::Pomodoro, SessionOutcome::Skipped);
+        skipped.task = Some(String::from("Report"));
+        let stats = Stats::new(&[logged, tagged, skipped], &tasks, date(14));
+
+        // Sessions without a project of their own take the task's
+        assert_eq!(names(&stats.projects), vec![Some("Home"), Some("Work")]);
+        assert_eq!(stats.projects[1].pomodoros_logged, 1);
+        assert_eq!(stats.projects[1].focus_minutes, 50);
+        assert_eq!(stats.projects[0].pomodoros_logged, 1);
+        assert_eq!(stats.projects[0].pomodoros_expected, 0);
+    }
+}
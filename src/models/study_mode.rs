@@ -1,4 +1,10 @@
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StudyMode {
     Normal,
     Zen,
+    /// Statistics worked out from the session history.
+    Stats,
 }
@@ -1,47 +1,109 @@
-use std::{fs, path::Path};
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::paths::write_atomic;
+
+use super::stateful_list::{parent, subtree, Nested};
 
 pub struct Task {
     pub title: String,
     pub pomodoros_expected: u16,
     pub pomodoros_completed: u16,
     pub completed: bool,
+    /// How deeply the task is nested, 0 for a top level task. Subtasks follow their parent.
+    pub depth: usize,
+    /// Whether the task's subtasks are hidden in the task list.
+    pub collapsed: bool,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl Task {
-    pub fn from_file(path: &Path) -> Vec<Self> {
-        let raw_tasks = match fs::read_to_string(path) {
-            Ok(content) => content,
-            Err(_) => return vec![],
-        };
-
-        let sections = raw_tasks
-            .split("---")
-            .map(|section| section.trim())
-            .filter(|section| !section.is_empty())
-            .collect::<Vec<&str>>();
-
-        let tasks: Vec<Task> = sections
-            .iter()
-            .map(|section| {
-                let section_items = section
-                    .lines()
-                    .flat_map(|line| line.split(":").skip(1).map(|val| val.trim()))
-                    .collect::<Vec<&str>>();
-
-                // Todo: is there a better way of doing this?
-                Task {
-                    title: section_items[0].to_string(),
-                    pomodoros_expected: section_items[1].parse().unwrap_or_default(),
-                    pomodoros_completed: section_items[2].parse().unwrap_or_default(),
-                    completed: section_items[3].parse().unwrap_or_default(),
+    pub fn new(title: String, pomodoros_expected: u16) -> Self {
+        Self {
+            title,
+            pomodoros_expected,
+            pomodoros_completed: 0,
+            completed: false,
+            depth: 0,
+            collapsed: false,
+            project: None,
+            tags: vec![],
+        }
+    }
+
+    /// Split a comma separated list of tags, dropping any `#` they're written with.
+    pub fn parse_tags(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches('#').trim())
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Read and parse the tasks stored at `path`.
+    pub fn from_file(path: &Path) -> Result<Vec<Self>, TaskFileError> {
+        let raw_tasks = fs::read_to_string(path).map_err(|source| TaskFileError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Task::parse(&raw_tasks).map_err(|(line, reason)| TaskFileError::Parse {
+            path: path.to_path_buf(),
+            line,
+            reason,
+        })
+    }
+
+    /// Parse the task file format. Tasks are separated by `---` lines and are made up of
+    /// `key: value` lines which may appear in any order. Only `title` is required. Subtasks
+    /// follow their parent with a greater `depth`.
+    ///
+    /// On failure the 1-indexed line number is returned along with the reason.
+    pub fn parse(raw_tasks: &str) -> Result<Vec<Self>, (usize, ParseErrorReason)> {
+        let mut tasks = vec![];
+        let mut section: Option<(usize, TaskFields)> = None;
+
+        for (index, line) in raw_tasks.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+
+            if line == "---" {
+                if let Some((start, fields)) = section.take() {
+                    tasks.push(fields.build(&tasks).map_err(|reason| (start, reason))?);
                 }
-            })
-            .collect();
 
-        tasks
+                continue;
+            }
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once(':')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or((line_number, ParseErrorReason::MissingSeparator))?;
+
+            let (_, fields) = section.get_or_insert_with(|| (line_number, TaskFields::default()));
+            fields
+                .set(key, value)
+                .map_err(|reason| (line_number, reason))?;
+        }
+
+        if let Some((start, fields)) = section {
+            tasks.push(fields.build(&tasks).map_err(|reason| (start, reason))?);
+        }
+
+        Ok(tasks)
     }
 
-    pub fn save(path: &Path, tasks: &Vec<Self>) -> Result<(), std::io::Error> {
+    /// Write the tasks to `path`, replacing it atomically.
+    pub fn save(path: &Path, tasks: &[Self]) -> Result<(), std::io::Error> {
         let mut content_string = String::new();
 
         for task in tasks.iter() {
@@ -52,23 +114,85 @@ impl Task {
             )
                 .as_str(),
             );
+
+            // Left out for top level and expanded tasks, so flat task lists look as they did
+            if task.depth > 0 {
+                content_string.push_str(&format!("depth: {}\n", task.depth));
+            }
+            if task.collapsed {
+                content_string.push_str("collapsed: true\n");
+            }
+            if let Some(project) = &task.project {
+                content_string.push_str(&format!("project: {}\n", project));
+            }
+            if !task.tags.is_empty() {
+                content_string.push_str(&format!("tags: {}\n", task.tags.join(", ")));
+            }
         }
 
         content_string.push_str("---");
 
-        fs::write(path, content_string)
+        write_atomic(path, &content_string)
     }
 }
 
 impl Task {
-    pub fn list_print(&self) -> String {
-        format!(
+    /// Pomodoros completed and expected for the task at `index`, rolled up from its subtasks.
+    pub fn pomodoros(tasks: &[Task], index: usize) -> (u16, u16) {
+        tasks[subtree(tasks, index)]
+            .iter()
+            .fold((0, 0), |(completed, expected), task| {
+                (
+                    completed.saturating_add(task.pomodoros_completed),
+                    expected.saturating_add(task.pomodoros_expected),
+                )
+            })
+    }
+
+    /// The titles of the task at `index` and the tasks it's nested under, outermost first. Tells
+    /// apart tasks with the same title, such as subtasks of different tasks.
+    pub fn path(tasks: &[Task], index: usize) -> Vec<String> {
+        let mut path = vec![tasks[index].title.clone()];
+        let mut child = index;
+
+        while let Some(parent) = parent(tasks, child) {
+            path.push(tasks[parent].title.clone());
+            child = parent;
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// The index of the task with the given `path`, `None` if there isn't exactly one.
+    pub fn find_by_path(tasks: &[Task], path: &[String]) -> Option<usize> {
+        let mut matches = (0..tasks.len()).filter(|&index| Task::path(tasks, index) == path);
+
+        match (matches.next(), matches.next()) {
+            (Some(index), None) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// The task as shown in the task list, with pomodoro counts rolled up from its subtasks.
+    /// The project and tags follow the title, written the way the filter matches them.
+    pub fn list_print(&self, (pomodoros_completed, pomodoros_expected): (u16, u16)) -> String {
+        let mut line = format!(
             "[{}] | {}/{} - {}",
             if self.completed { "x" } else { " " },
-            self.pomodoros_completed,
-            self.pomodoros_expected,
+            pomodoros_completed,
+            pomodoros_expected,
             self.title
-        )
+        );
+
+        if let Some(project) = &self.project {
+            line.push_str(&format!(" @{}", project));
+        }
+        for tag in &self.tags {
+            line.push_str(&format!(" #{}", tag));
+        }
+
+        line
     }
 
     pub fn complete_task(&mut self) {
@@ -85,3 +209,334 @@ impl Task {
         }
     }
 }
+
+impl Nested for Task {
+    fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn collapsed(&self) -> bool {
+        self.collapsed
+    }
+
+    fn set_collapsed(&mut self, collapsed: bool) {
+        self.collapsed = collapsed;
+    }
+}
+
+/// Fields collected for a single task while parsing.
+#[derive(Default)]
+struct TaskFields {
+    title: Option<String>,
+    pomodoros_expected: Option<u16>,
+    pomodoros_completed: Option<u16>,
+    completed: Option<bool>,
+    depth: Option<usize>,
+    collapsed: Option<bool>,
+    project: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+impl TaskFields {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), ParseErrorReason> {
+        fn parse_value<T: std::str::FromStr>(
+            key: &str,
+            value: &str,
+        ) -> Result<T, ParseErrorReason> {
+            value.parse().map_err(|_| ParseErrorReason::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            })
+        }
+
+        fn replace<T>(field: &mut Option<T>, key: &str, value: T) -> Result<(), ParseErrorReason> {
+            match field.replace(value) {
+                Some(_) => Err(ParseErrorReason::DuplicateKey(key.to_string())),
+                None => Ok(()),
+            }
+        }
+
+        match key {
+            "title" => replace(&mut self.title, key, value.to_string()),
+            "pomodoros_expected" => {
+                replace(&mut self.pomodoros_expected, key, parse_value(key, value)?)
+            }
+            "pomodoros_completed" => {
+                replace(&mut self.pomodoros_completed, key, parse_value(key, value)?)
+            }
+            "completed" => replace(&mut self.completed, key, parse_value(key, value)?),
+            "depth" => replace(&mut self.depth, key, parse_value(key, value)?),
+            "collapsed" => replace(&mut self.collapsed, key, parse_value(key, value)?),
+            "project" => replace(&mut self.project, key, value.to_string()),
+            "tags" => replace(&mut self.tags, key, Task::parse_tags(value)),
+            _ => Err(ParseErrorReason::UnknownKey(key.to_string())),
+        }
+    }
+
+    /// Build the task, following on from the tasks parsed before it.
+    fn build(self, previous: &[Task]) -> Result<Task, ParseErrorReason> {
+        let title = self
+            .title
+            .filter(|title| !title.is_empty())
+            .ok_or(ParseErrorReason::MissingTitle)?;
+
+        // A subtask can be at most one level deeper than the task above it
+        let depth = self.depth.unwrap_or_default();
+        if depth > previous.last().map_or(0, |task| task.depth + 1) {
+            return Err(ParseErrorReason::MissingParent(depth));
+        }
+
+        Ok(Task {
+            title,
+            pomodoros_expected: self.pomodoros_expected.unwrap_or_default(),
+            pomodoros_completed: self.pomodoros_completed.unwrap_or_default(),
+            completed: self.completed.unwrap_or_default(),
+            depth,
+            collapsed: self.collapsed.unwrap_or_default(),
+            project: self.project.filter(|project| !project.is_empty()),
+            tags: self.tags.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseErrorReason {
+    /// The line isn't in the `key: value` format.
+    MissingSeparator,
+    UnknownKey(String),
+    DuplicateKey(String),
+    InvalidValue {
+        key: String,
+        value: String,
+    },
+    MissingTitle,
+    /// The task's `depth` is more than one level below the task before it.
+    MissingParent(usize),
+}
+
+impl fmt::Display for ParseErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorReason::MissingSeparator => write!(f, "expected a `key: value` line"),
+            ParseErrorReason::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+            ParseErrorReason::DuplicateKey(key) => write!(f, "`{}` is set more than once", key),
+            ParseErrorReason::InvalidValue { key, value } => {
+                write!(f, "invalid value `{}` for `{}`", value, key)
+            }
+            ParseErrorReason::MissingTitle => write!(f, "task is missing a `title`"),
+            ParseErrorReason::MissingParent(depth) => {
+                write!(f, "task at depth {} has no parent task above it", depth)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TaskFileError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        reason: ParseErrorReason,
+    },
+}
+
+impl TaskFileError {
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, TaskFileError::Io { source, .. } if source.kind() == io::ErrorKind::NotFound)
+    }
+}
+
+impl fmt::Display for TaskFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskFileError::Io { path, source } => {
+                write!(f, "{}: {}", path.display(), source)
+            }
+            TaskFileError::Parse { path, line, reason } => {
+                write!(f, "{}:{}: {}", path.display(), line, reason)
+            }
+        }
+    }
+}
+
+impl Error for TaskFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TaskFileError::Io { source, .. } => Some(source),
+            TaskFileError::Parse { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line and message `Task::parse` fails with.
+    fn parse_error(raw_tasks: &str) -> (usize, String) {
+        match Task::parse(raw_tasks) {
+            Ok(_) => panic!("expected {:?} to fail to parse", raw_tasks),
+            Err((line, reason)) => (line, reason.to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_fields_in_any_order_with_defaults() {
+        let tasks = Task::parse(
+            "---\ncompleted: true\ntitle: Write report\n---\ntitle: Read\npomodoros_expected: 2\n---",
+        )
+        .unwrap();
+
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].title, "Write report");
+        assert!(tasks[0].completed);
+        assert_eq!(tasks[0].pomodoros_expected, 0);
+        assert_eq!(tasks[1].pomodoros_expected, 2);
+        assert_eq!(tasks[1].pomodoros_completed, 0);
+    }
+
+    #[test]
+    fn missing_title_is_reported_at_the_start_of_the_task() {
+        assert_eq!(
+            parse_error("---\ntitle: One\n---\npomodoros_expected: 2\ncompleted: false\n---"),
+            (4, String::from("task is missing a `title`"))
+        );
+    }
+
+    #[test]
+    fn empty_title_counts_as_missing() {
+        assert_eq!(
+            parse_error("title:\n"),
+            (1, String::from("task is missing a `title`"))
+        );
+    }
+
+    #[test]
+    fn duplicate_key_is_reported_on_its_second_line() {
+        assert_eq!(
+            parse_error("---\ntitle: One\npomodoros_expected: 1\ntitle: Two\n---"),
+            (4, String::from("`title` is set more than once"))
+        );
+    }
+
+    #[test]
+    fn unknown_key_is_reported_on_its_line() {
+        assert_eq!(
+            parse_error("---\ntitle: One\n\npriority: high\n---"),
+            (4, String::from("unknown key `priority`"))
+        );
+    }
+
+    #[test]
+    fn bad_value_is_reported_on_its_line() {
+        assert_eq!(
+            parse_error("---\ntitle: One\npomodoros_expected: lots\n---"),
+            (
+                3,
+                String::from("invalid value `lots` for `pomodoros_expected`")
+            )
+        );
+        assert_eq!(
+            parse_error("title: One\ncompleted: yes\n"),
+            (2, String::from("invalid value `yes` for `completed`"))
+        );
+    }
+
+    #[test]
+    fn line_without_separator_is_reported() {
+        assert_eq!(
+            parse_error("---\ntitle: One\njust some text\n---"),
+            (3, String::from("expected a `key: value` line"))
+        );
+    }
+
+    #[test]
+    fn depth_without_a_parent_is_reported_at_the_start_of_the_task() {
+        assert_eq!(
+            parse_error("---\ntitle: Orphan\ndepth: 1\n---"),
+            (
+                2,
+                String::from("task at depth 1 has no parent task above it")
+            )
+        );
+        assert_eq!(
+            parse_error("---\ntitle: One\n---\ntitle: Too deep\ndepth: 2\n---"),
+            (
+                4,
+                String::from("task at depth 2 has no parent task above it")
+            )
+        );
+    }
+
+    #[test]
+    fn path_tells_apart_subtasks_with_the_same_title() {
+        let tasks = Task::parse(
+            "title: Report\n---\ntitle: Review\ndepth: 1\n---\ntitle: Slides\n---\ntitle: Review\ndepth: 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(Task::path(&tasks, 1), vec!["Report", "Review"]);
+        assert_eq!(Task::path(&tasks, 3), vec!["Slides", "Review"]);
+        assert_eq!(Task::path(&tasks, 2), vec!["Slides"]);
+    }
+
+    #[test]
+    fn find_by_path_needs_exactly_one_match() {
+        let tasks = Task::parse(
+            "title: Report\n---\ntitle: Review\ndepth: 1\n---\ntitle: Slides\n---\ntitle: Review\ndepth: 1\n---\ntitle: Slides\n",
+        )
+        .unwrap();
+        let path =
+            |titles: &[&str]| -> Vec<String> { titles.iter().map(|t| t.to_string()).collect() };
+
+        assert_eq!(
+            Task::find_by_path(&tasks, &path(&["Slides", "Review"])),
+            Some(3)
+        );
+        assert_eq!(Task::find_by_path(&tasks, &path(&["Review"])), None);
+        assert_eq!(Task::find_by_path(&tasks, &path(&["Slides"])), None);
+    }
+
+    /// Save `tasks` to a scratch file and read them back.
+    fn save_and_load(name: &str, tasks: &[Task]) -> Vec<Task> {
+        let path = std::env::temp_dir().join(format!("pomodoro-{}-{}", name, std::process::id()));
+        Task::save(&path, tasks).unwrap();
+        let loaded = Task::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn saved_subtasks_keep_their_depth() {
+        let mut subtask = Task::new(String::from("Draft"), 2);
+        subtask.depth = 1;
+        let mut nested = Task::new(String::from("Outline"), 1);
+        nested.depth = 2;
+
+        let tasks = save_and_load(
+            "depth",
+            &[Task::new(String::from("Write report"), 3), subtask, nested],
+        );
+
+        let depths: Vec<usize> = tasks.iter().map(|task| task.depth).collect();
+        assert_eq!(depths, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn saved_tasks_keep_their_project_and_tags() {
+        let mut task = Task::new(String::from("Write report"), 3);
+        task.project = Some(String::from("Work"));
+        task.tags = vec![String::from("writing"), String::from("q3")];
+
+        let tasks = save_and_load("project", &[task, Task::new(String::from("Read"), 1)]);
+
+        assert_eq!(tasks[0].project.as_deref(), Some("Work"));
+        assert_eq!(tasks[0].tags, vec!["writing", "q3"]);
+        assert_eq!(tasks[1].project, None);
+        assert!(tasks[1].tags.is_empty());
+    }
+}
@@ -1,20 +1,36 @@
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
 
 use super::pomodoro_mode::PomodoroMode;
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TimerStatus {
     Playing,
     Paused,
 }
 
+/// A countdown (or count-up) timer measured against the wall clock.
+///
+/// Rather than decrementing a counter on every tick, the timer remembers when it was first
+/// started and how long it has spent paused since, so the time remaining is always worked out
+/// from `Instant`s and can't drift no matter how often (or how rarely) it's polled.
 pub struct Timer {
     pub status: TimerStatus,
-    pub time_remaining: Duration,
-    pub total_time: Duration,
-    pub percentage: u16,
+    /// Length of the timer, `None` if it counts up until stopped.
+    pub total_time: Option<Duration>,
     pub pomodoro_mode: PomodoroMode,
-    pub ticked: bool,
+    /// When the timer was first unpaused, `None` if it has never been started.
+    started_at: Option<(Instant, SystemTime)>,
+    paused_at: Option<Instant>,
+    paused_time: Duration,
+    /// Time spent running and paused before the timer was restored from a snapshot, which
+    /// `started_at` and `paused_time` don't cover.
+    elapsed_before_restore: Duration,
+    paused_before_restore: Duration,
+    /// Set once the user has been told the timer finished and it's counting overtime.
+    pub overtime_notified: bool,
 }
 
 fn calculate_time_as_percentage(total_time: f32, time_left: f32) -> u16 {
@@ -22,55 +38,337 @@ fn calculate_time_as_percentage(total_time: f32, time_left: f32) -> u16 {
 }
 
 impl Timer {
-    pub fn tick(&mut self) {
-        self.time_remaining = Duration::from(self.time_remaining) - Duration::from_secs(1);
-        self.percentage = calculate_time_as_percentage(
-            self.total_time.as_secs() as f32,
-            self.time_remaining.as_secs() as f32,
-        );
-    }
-
     pub fn pause(&mut self) {
-        self.status = TimerStatus::Paused;
+        if self.status == TimerStatus::Playing {
+            self.status = TimerStatus::Paused;
+            self.paused_at = Some(Instant::now());
+        }
     }
 
     pub fn unpause(&mut self) {
+        if self.started_at.is_none() {
+            self.started_at = Some((Instant::now(), SystemTime::now()));
+            self.paused_at = None;
+        } else if let Some(paused_at) = self.paused_at.take() {
+            self.paused_time += paused_at.elapsed();
+        }
+
         self.status = TimerStatus::Playing;
     }
+
+    /// Time spent running, excluding any time spent paused.
+    pub fn elapsed(&self) -> Duration {
+        match self.started_at {
+            Some((started_at, _)) => {
+                self.elapsed_before_restore
+                    + self
+                        .paused_at
+                        .unwrap_or_else(Instant::now)
+                        .duration_since(started_at)
+                        .saturating_sub(self.paused_time)
+            }
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Time spent paused since the timer was first started.
+    pub fn paused_time(&self) -> Duration {
+        self.paused_before_restore
+            + self.paused_time
+            + self
+                .paused_at
+                .map_or(Duration::ZERO, |paused_at| paused_at.elapsed())
+    }
+
+    /// The wall-clock time the timer was first started at.
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.started_at.map(|(_, started_at)| started_at)
+    }
+
+    pub fn is_count_up(&self) -> bool {
+        self.total_time.is_none()
+    }
+
+    /// Time left until the timer finishes, always zero for count-up timers.
+    pub fn time_remaining(&self) -> Duration {
+        self.total_time.map_or(Duration::ZERO, |total_time| {
+            total_time.saturating_sub(self.elapsed())
+        })
+    }
+
+    /// How far through the timer is, always zero for count-up timers.
+    pub fn percentage(&self) -> u16 {
+        match self.total_time {
+            Some(total_time) => calculate_time_as_percentage(
+                total_time.as_secs_f32(),
+                self.time_remaining().as_secs_f32(),
+            ),
+            None => 0,
+        }
+    }
+
+    /// Whether a countdown has reached zero. Count-up timers never finish on their own.
+    pub fn is_finished(&self) -> bool {
+        self.total_time.is_some() && self.time_remaining().is_zero()
+    }
+
+    /// Time spent running past the end of the timer.
+    pub fn overtime(&self) -> Duration {
+        self.total_time.map_or(Duration::ZERO, |total_time| {
+            self.elapsed().saturating_sub(total_time)
+        })
+    }
 }
 
 impl Timer {
+    /// Whole seconds left on the timer, rounded up so the display only reads zero once the
+    /// timer has actually finished.
+    pub fn remaining_secs(&self) -> u64 {
+        let remaining = self.time_remaining();
+
+        if remaining.subsec_nanos() > 0 {
+            remaining.as_secs() + 1
+        } else {
+            remaining.as_secs()
+        }
+    }
+
+    /// Seconds shown on the clock: the time remaining, or the time elapsed for count-up timers.
+    pub fn display_secs(&self) -> u64 {
+        if self.is_count_up() {
+            self.elapsed().as_secs()
+        } else {
+            self.remaining_secs()
+        }
+    }
+
     fn get_seconds(&self) -> u64 {
-        self.time_remaining.as_secs() % 60
+        self.display_secs() % 60
     }
 
     fn get_minutes(&self) -> u64 {
-        (self.time_remaining.as_secs() / 60) % 60
+        (self.display_secs() / 60) % 60
     }
 
     fn get_hours(&self) -> u64 {
-        (self.time_remaining.as_secs() / 60) / 60
+        (self.display_secs() / 60) / 60
     }
 
     pub fn mm_ss(&self) -> String {
         format!("{}:{}", self.get_minutes(), self.get_seconds())
     }
 
+    pub fn overtime_mm_ss(&self) -> String {
+        let overtime = self.overtime().as_secs();
+
+        format!("+{:02}:{:02}", overtime / 60, overtime % 60)
+    }
+
     pub fn hh_mm_ss(&self) -> String {
-        format!("{}:{}:{}", self.get_hours(), self.get_minutes(), self.get_seconds())
+        format!(
+            "{}:{}:{}",
+            self.get_hours(),
+            self.get_minutes(),
+            self.get_seconds()
+        )
+    }
+
+    /// The clock as `mm:ss`, zero-padded for status bars. Minutes carry on past the hour.
+    pub fn padded_mm_ss(&self) -> String {
+        format!("{:02}:{:02}", self.display_secs() / 60, self.get_seconds())
+    }
+
+    /// The clock as `hh:mm:ss`, zero-padded for status bars.
+    pub fn padded_hh_mm_ss(&self) -> String {
+        format!(
+            "{:02}:{:02}:{:02}",
+            self.get_hours(),
+            self.get_minutes(),
+            self.get_seconds()
+        )
     }
 }
 
 impl Timer {
     pub fn new(timer: Duration, mode: PomodoroMode) -> Self {
+        Self::with_total_time(Some(timer), mode)
+    }
+
+    /// Create a timer that counts up from zero until it's stopped.
+    pub fn count_up(mode: PomodoroMode) -> Self {
+        Self::with_total_time(None, mode)
+    }
+
+    /// A fresh timer of the same length and mode.
+    pub fn restarted(&self) -> Self {
+        Self::with_total_time(self.total_time, self.pomodoro_mode)
+    }
+
+    fn with_total_time(total_time: Option<Duration>, mode: PomodoroMode) -> Self {
         Self {
             status: TimerStatus::Paused,
-            time_remaining: timer,
-            percentage: 0,
-            total_time: timer,
+            total_time,
             pomodoro_mode: mode,
-            ticked: false,
+            started_at: None,
+            paused_at: None,
+            paused_time: Duration::ZERO,
+            elapsed_before_restore: Duration::ZERO,
+            paused_before_restore: Duration::ZERO,
+            overtime_notified: false,
         }
+    }
+}
+
+/// What's needed to recreate a timer after the application restarts.
+#[derive(Serialize, Deserialize)]
+pub struct TimerSnapshot {
+    pub status: TimerStatus,
+    pub total_time: Option<Duration>,
+    pub mode: PomodoroMode,
+    pub started_at: Option<SystemTime>,
+    pub elapsed: Duration,
+    pub paused_time: Duration,
+    pub overtime_notified: bool,
+}
+
+impl Timer {
+    pub fn snapshot(&self) -> TimerSnapshot {
+        TimerSnapshot {
+            status: self.status,
+            total_time: self.total_time,
+            mode: self.pomodoro_mode,
+            started_at: self.started_at(),
+            elapsed: self.elapsed(),
+            paused_time: self.paused_time(),
+            overtime_notified: self.overtime_notified,
+        }
+    }
+
+    /// Recreate a timer from a snapshot taken `away` ago. A timer that was running keeps
+    /// counting through the time away, a paused one picks up where it left off.
+    pub fn restore(snapshot: &TimerSnapshot, away: Duration) -> Self {
+        let mut timer = Self::with_total_time(snapshot.total_time, snapshot.mode);
+        timer.overtime_notified = snapshot.overtime_notified;
+
+        if let Some(started_at) = snapshot.started_at {
+            let now = Instant::now();
+
+            timer.status = snapshot.status;
+            timer.started_at = Some((now, started_at));
+            timer.paused_before_restore = snapshot.paused_time;
+            timer.elapsed_before_restore = match snapshot.status {
+                TimerStatus::Playing => snapshot.elapsed + away,
+                TimerStatus::Paused => {
+                    timer.paused_at = Some(now);
+                    snapshot.elapsed
+                }
+            };
+        }
+
+        timer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_clock_keeps_two_digits() {
+        let timer = Timer::new(Duration::from_secs(24 * 60 + 5), PomodoroMode::Pomodoro);
+
+        assert_eq!(timer.padded_mm_ss(), "24:05");
+        assert_eq!(timer.padded_hh_mm_ss(), "00:24:05");
+    }
+
+    #[test]
+    fn padded_minutes_carry_on_past_the_hour() {
+        let timer = Timer::new(Duration::from_secs(90 * 60), PomodoroMode::Pomodoro);
+
+        assert_eq!(timer.padded_mm_ss(), "90:00");
+        assert_eq!(timer.padded_hh_mm_ss(), "01:30:00");
+    }
+
+    fn snapshot(status: TimerStatus, elapsed: u64, paused_time: u64) -> TimerSnapshot {
+        TimerSnapshot {
+            status,
+            total_time: Some(Duration::from_secs(25 * 60)),
+            mode: PomodoroMode::Pomodoro,
+            started_at: Some(SystemTime::UNIX_EPOCH),
+            elapsed: Duration::from_secs(elapsed),
+            paused_time: Duration::from_secs(paused_time),
+            overtime_notified: false,
+        }
+    }
+
+    #[test]
+    fn restored_running_timer_counts_the_time_away() {
+        let timer = Timer::restore(
+            &snapshot(TimerStatus::Playing, 10 * 60, 30),
+            Duration::from_secs(5 * 60),
+        );
+
+        assert!(timer.status == TimerStatus::Playing);
+        assert_eq!(timer.elapsed().as_secs(), 15 * 60);
+        assert_eq!(timer.paused_time().as_secs(), 30);
+        assert_eq!(timer.started_at(), Some(SystemTime::UNIX_EPOCH));
+    }
+
+    #[test]
+    fn restored_paused_timer_picks_up_where_it_left_off() {
+        let timer = Timer::restore(
+            &snapshot(TimerStatus::Paused, 10 * 60, 30),
+            Duration::from_secs(5 * 60),
+        );
+
+        assert!(timer.status == TimerStatus::Paused);
+        assert_eq!(timer.elapsed(), Duration::from_secs(10 * 60));
+        assert_eq!(timer.time_remaining(), Duration::from_secs(15 * 60));
+        assert_eq!(timer.paused_time().as_secs(), 30);
+    }
+
+    #[test]
+    fn restored_timer_that_ran_out_while_away_is_in_overtime() {
+        let mut snapshot = snapshot(TimerStatus::Playing, 20 * 60, 0);
+        snapshot.overtime_notified = true;
+        let timer = Timer::restore(&snapshot, Duration::from_secs(10 * 60));
+
+        assert!(timer.is_finished());
+        assert_eq!(timer.overtime().as_secs(), 5 * 60);
+        assert!(timer.overtime_notified);
+    }
+
+    #[test]
+    fn restored_timer_that_never_started_stays_unstarted() {
+        let mut snapshot = snapshot(TimerStatus::Playing, 0, 0);
+        snapshot.started_at = None;
+        let timer = Timer::restore(&snapshot, Duration::from_secs(5 * 60));
+
+        assert!(timer.status == TimerStatus::Paused);
+        assert_eq!(timer.started_at(), None);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn snapshot_round_trips_a_count_up_timer() {
+        let mut timer = Timer::count_up(PomodoroMode::Flowtime);
+        timer.unpause();
+        timer.pause();
+        let restored = Timer::restore(&timer.snapshot(), Duration::from_secs(60));
+
+        assert!(restored.is_count_up());
+        assert!(restored.pomodoro_mode == PomodoroMode::Flowtime);
+        assert!(!restored.is_finished());
+        assert_eq!(restored.elapsed().as_secs(), 0);
+    }
+
+    #[test]
+    fn overtime_clock_keeps_two_digits() {
+        let timer = Timer::restore(
+            &snapshot(TimerStatus::Paused, 25 * 60 + 65, 0),
+            Duration::ZERO,
+        );
 
+        assert_eq!(timer.overtime_mm_ss(), "+01:05");
     }
 }
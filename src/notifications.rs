@@ -0,0 +1,300 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+#[cfg(feature = "desktop-notifications")]
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use serde::Deserialize;
+
+use crate::models::pomodoro_mode::PomodoroMode;
+
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+impl Notification {
+    /// The notification sent when a timer runs out.
+    pub fn period_finished(finished: PomodoroMode, next: PomodoroMode, task: Option<&str>) -> Self {
+        let title = format!("{} finished", finished);
+        let body = match (next, task) {
+            (PomodoroMode::Pomodoro | PomodoroMode::Flowtime, Some(task)) => {
+                format!("Back to work on {}", task)
+            }
+            (PomodoroMode::Pomodoro | PomodoroMode::Flowtime, None) => String::from("Back to work"),
+            (PomodoroMode::ShortBreak | PomodoroMode::LongBreak, _) => {
+                format!("Time for a {}", next.to_string().to_lowercase())
+            }
+        };
+
+        Self { title, body }
+    }
+}
+
+/// A way of getting the user's attention.
+pub trait Notifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>>;
+}
+
+/// Escape sequences understood by the terminal itself.
+pub enum TerminalNotification {
+    Bell,
+    /// `OSC 9`, supported by iTerm2, Windows Terminal, kitty and others.
+    Osc9,
+    /// `OSC 777`, supported by rxvt-unicode, foot and VTE based terminals.
+    Osc777,
+}
+
+/// Writes a `TerminalNotification` to the terminal the application is running in.
+pub struct TerminalNotifier<W: Write> {
+    pub kind: TerminalNotification,
+    pub writer: W,
+}
+
+/// Remove control characters that would end the escape sequence early.
+fn sanitise(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+impl<W: Write> Notifier for TerminalNotifier<W> {
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        let title = sanitise(&notification.title);
+        let body = sanitise(&notification.body);
+
+        match self.kind {
+            TerminalNotification::Bell => write!(self.writer, "\x07")?,
+            TerminalNotification::Osc9 => write!(self.writer, "\x1b]9;{}: {}\x07", title, body)?,
+            TerminalNotification::Osc777 => {
+                write!(self.writer, "\x1b]777;notify;{};{}\x07", title, body)?
+            }
+        }
+
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Sends freedesktop notifications over the D-Bus session bus given by
+/// `DBUS_SESSION_BUS_ADDRESS`. Each one is sent from its own thread, as the bus can be slow to
+/// answer, or not answer at all over SSH.
+#[cfg(feature = "desktop-notifications")]
+#[derive(Default)]
+pub struct DesktopNotifier {
+    /// Why a notification couldn't be sent, set by the thread sending it.
+    failure: Arc<Mutex<Option<String>>>,
+    /// Set once a failure has been reported, after which nothing more is sent.
+    disabled: bool,
+}
+
+#[cfg(feature = "desktop-notifications")]
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        if self.disabled {
+            return Ok(());
+        }
+
+        // A missing bus stays missing, so report it the once rather than on every period
+        if let Some(failure) = self
+            .failure
+            .lock()
+            .ok()
+            .and_then(|mut failure| failure.take())
+        {
+            self.disabled = true;
+            return Err(
+                format!("{}, desktop notifications are off until restarted", failure).into(),
+            );
+        }
+
+        let failure = Arc::clone(&self.failure);
+        let title = notification.title.clone();
+        let body = notification.body.clone();
+
+        thread::spawn(move || {
+            let result = notify_rust::Notification::new()
+                .appname("pomodoro")
+                .summary(&title)
+                .body(&body)
+                .show();
+
+            if let (Err(err), Ok(mut failure)) = (result, failure.lock()) {
+                *failure = Some(err.to_string());
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Which backends to notify through, configured under `[notifications]`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct NotificationsConfig {
+    pub bell: bool,
+    pub osc9: bool,
+    pub osc777: bool,
+    pub desktop: bool,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            bell: true,
+            osc9: false,
+            osc777: false,
+            desktop: true,
+        }
+    }
+}
+
+/// Every enabled `Notifier`.
+pub struct Notifications {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl Notifications {
+    pub fn new(notifiers: Vec<Box<dyn Notifier>>) -> Self {
+        Self { notifiers }
+    }
+
+    pub fn from_config(config: &NotificationsConfig) -> Self {
+        let mut notifiers: Vec<Box<dyn Notifier>> = vec![];
+
+        let terminal_notifications = [
+            (config.bell, TerminalNotification::Bell),
+            (config.osc9, TerminalNotification::Osc9),
+            (config.osc777, TerminalNotification::Osc777),
+        ];
+        for (enabled, kind) in terminal_notifications {
+            if enabled {
+                notifiers.push(Box::new(TerminalNotifier {
+                    kind,
+                    writer: io::stdout(),
+                }));
+            }
+        }
+
+        #[cfg(feature = "desktop-notifications")]
+        if config.desktop {
+            notifiers.push(Box::new(DesktopNotifier::default()));
+        }
+
+        Self::new(notifiers)
+    }
+
+    /// Send the notification through every backend, returning the errors of any that failed.
+    pub fn notify(&mut self, notification: &Notification) -> Vec<Box<dyn Error>> {
+        self.notifiers
+            .iter_mut()
+            .filter_map(|notifier| notifier.notify(notification).err())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    fn notification() -> Notification {
+        Notification {
+            title: String::from("Pomodoro finished"),
+            body: String::from("Time for a short break"),
+        }
+    }
+
+    fn terminal_output(kind: TerminalNotification, notification: &Notification) -> String {
+        let mut notifier = TerminalNotifier {
+            kind,
+            writer: Vec::new(),
+        };
+        notifier.notify(notification).unwrap();
+
+        String::from_utf8(notifier.writer).unwrap()
+    }
+
+    #[test]
+    fn terminal_notifications_write_their_escape_sequences() {
+        let notification = notification();
+
+        assert_eq!(
+            terminal_output(TerminalNotification::Bell, &notification),
+            "\x07"
+        );
+        assert_eq!(
+            terminal_output(TerminalNotification::Osc9, &notification),
+            "\x1b]9;Pomodoro finished: Time for a short break\x07"
+        );
+        assert_eq!(
+            terminal_output(TerminalNotification::Osc777, &notification),
+            "\x1b]777;notify;Pomodoro finished;Time for a short break\x07"
+        );
+    }
+
+    #[test]
+    fn terminal_notifications_drop_control_characters() {
+        let notification = Notification {
+            title: String::from("Task\x07 done"),
+            body: String::from("\x1b]9;injected"),
+        };
+
+        assert_eq!(
+            terminal_output(TerminalNotification::Osc9, &notification),
+            "\x1b]9;Task done: ]9;injected\x07"
+        );
+    }
+
+    /// Remembers the titles it was sent, or fails every time.
+    struct FakeNotifier {
+        sent: Rc<RefCell<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl Notifier for FakeNotifier {
+        fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+            if self.fail {
+                return Err("no session bus".into());
+            }
+
+            self.sent.borrow_mut().push(notification.title.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn notifications_go_to_every_backend_and_collect_failures() {
+        let sent = Rc::new(RefCell::new(vec![]));
+        let mut notifications = Notifications::new(vec![
+            Box::new(FakeNotifier {
+                sent: Rc::clone(&sent),
+                fail: true,
+            }),
+            Box::new(FakeNotifier {
+                sent: Rc::clone(&sent),
+                fail: false,
+            }),
+        ]);
+
+        let errors = notifications.notify(&notification());
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "no session bus");
+        assert_eq!(*sent.borrow(), vec![String::from("Pomodoro finished")]);
+    }
+
+    #[test]
+    fn period_finished_names_the_task_to_get_back_to() {
+        let notification = Notification::period_finished(
+            PomodoroMode::ShortBreak,
+            PomodoroMode::Pomodoro,
+            Some("Write report"),
+        );
+
+        assert_eq!(notification.title, "Short Break finished");
+        assert_eq!(notification.body, "Back to work on Write report");
+    }
+}
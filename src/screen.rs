@@ -0,0 +1,252 @@
+use tui::layout::{Margin, Rect};
+
+use crate::models::pomodoro_mode::PomodoroMode;
+
+/// Below this many rows or columns there's no room to draw the clock, so the timer shrinks to a
+/// single line.
+const COMPACT_HEIGHT: u16 = 16;
+const COMPACT_WIDTH: u16 = 40;
+/// From this many columns the timer and the tasks sit next to each other.
+const SIDE_BY_SIDE_WIDTH: u16 = 140;
+/// Rows kept for the task list when it's below the timer: its borders and a few tasks.
+const MIN_TASKS_HEIGHT: u16 = 5;
+/// Rows taken by the gauge in zen mode, including its borders.
+pub const GAUGE_HEIGHT: u16 = 3;
+
+/// How the main views are arranged, picked from the size of the terminal on every draw.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Arrangement {
+    /// A single line timer, for panes too small to draw the clock in.
+    Compact,
+    /// The timer above the tasks.
+    Stacked,
+    /// The timer and the tasks next to each other, for wide terminals.
+    SideBySide,
+}
+
+impl Arrangement {
+    pub fn for_size(size: Rect) -> Self {
+        if size.height < COMPACT_HEIGHT || size.width < COMPACT_WIDTH {
+            Arrangement::Compact
+        } else if size.width >= SIDE_BY_SIDE_WIDTH {
+            Arrangement::SideBySide
+        } else {
+            Arrangement::Stacked
+        }
+    }
+}
+
+/// Areas of the normal view.
+pub struct NormalAreas {
+    pub arrangement: Arrangement,
+    /// The timer pane, a single line when compact.
+    pub timer: Rect,
+    pub tasks: Rect,
+}
+
+/// Areas of zen mode.
+pub struct ZenAreas {
+    pub arrangement: Arrangement,
+    /// The timer pane, a single line when compact.
+    pub timer: Rect,
+    /// Inside the timer pane, or the line below the timer when compact.
+    pub gauge: Rect,
+}
+
+/// Areas inside the bordered timer pane.
+pub struct TimerPaneAreas {
+    pub watermark: Rect,
+    pub clock: Rect,
+}
+
+/// Areas inside the bordered task pane.
+pub struct TaskPaneAreas {
+    pub rows: Rect,
+    /// The filter bar, below the tasks, when it's shown.
+    pub filter: Option<Rect>,
+}
+
+/// Split `area` into its first `height` rows and the rest.
+fn split_rows(area: Rect, height: u16) -> (Rect, Rect) {
+    let height = height.min(area.height);
+
+    (
+        Rect { height, ..area },
+        Rect {
+            y: area.y + height,
+            height: area.height - height,
+            ..area
+        },
+    )
+}
+
+/// Split `area` into its first `width` columns and the rest.
+fn split_columns(area: Rect, width: u16) -> (Rect, Rect) {
+    let width = width.min(area.width);
+
+    (
+        Rect { width, ..area },
+        Rect {
+            x: area.x + width,
+            width: area.width - width,
+            ..area
+        },
+    )
+}
+
+fn with_margin(area: Rect) -> Rect {
+    area.inner(&Margin {
+        vertical: 1,
+        horizontal: 1,
+    })
+}
+
+pub fn normal(size: Rect) -> NormalAreas {
+    let arrangement = Arrangement::for_size(size);
+
+    let (timer, tasks) = match arrangement {
+        // Every row counts, so the margin goes too
+        Arrangement::Compact => split_rows(size, 1),
+        Arrangement::Stacked => {
+            let area = with_margin(size);
+            let tasks_height = (area.height * 2 / 5).max(MIN_TASKS_HEIGHT);
+
+            split_rows(area, area.height.saturating_sub(tasks_height))
+        }
+        Arrangement::SideBySide => {
+            let area = with_margin(size);
+
+            split_columns(area, area.width * 3 / 5)
+        }
+    };
+
+    NormalAreas {
+        arrangement,
+        timer,
+        tasks,
+    }
+}
+
+pub fn zen(size: Rect) -> ZenAreas {
+    let arrangement = Arrangement::for_size(size);
+
+    let (timer, gauge) = match arrangement {
+        Arrangement::Compact => {
+            let (timer, rest) = split_rows(size, 1);
+
+            (timer, split_rows(rest, 1).0)
+        }
+        Arrangement::Stacked | Arrangement::SideBySide => {
+            let timer = with_margin(size);
+            let inner = with_margin(timer);
+
+            (
+                timer,
+                split_rows(inner, inner.height.saturating_sub(GAUGE_HEIGHT)).1,
+            )
+        }
+    };
+
+    ZenAreas {
+        arrangement,
+        timer,
+        gauge,
+    }
+}
+
+/// Split the inside of the timer pane into the watermark line and the space for the clock,
+/// leaving out the last `reserved` rows, e.g. for the gauge.
+pub fn timer_pane(pane: Rect, reserved: u16) -> TimerPaneAreas {
+    let inner = with_margin(pane);
+    let (watermark, rest) = split_rows(inner, 1);
+    let clock = split_rows(rest, rest.height.saturating_sub(reserved)).0;
+
+    TimerPaneAreas { watermark, clock }
+}
+
+/// Split the inside of the task pane into the rows of tasks and, if `filter_bar`, the filter
+/// bar.
+pub fn task_pane(pane: Rect, filter_bar: bool) -> TaskPaneAreas {
+    let inner = with_margin(pane);
+
+    if filter_bar {
+        let (rows, filter) = split_rows(inner, inner.height.saturating_sub(1));
+
+        TaskPaneAreas {
+            rows,
+            filter: Some(filter),
+        }
+    } else {
+        TaskPaneAreas {
+            rows: inner,
+            filter: None,
+        }
+    }
+}
+
+/// A popup centered in `size` that's big enough for `width` by `height` of content plus its
+/// borders, but never bigger than the screen.
+pub fn popup(size: Rect, width: u16, height: u16) -> Rect {
+    let width = width.saturating_add(2).min(size.width);
+    let height = height.saturating_add(2).min(size.height);
+
+    Rect {
+        x: size.x + (size.width - width) / 2,
+        y: size.y + (size.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Where the clickable parts of the interface were last drawn, so mouse clicks can be matched
+/// to them.
+#[derive(Default)]
+pub struct ClickAreas {
+    pub timer: Option<Rect>,
+    /// The rows of the task list, and the number of rows scrolled past.
+    pub tasks: Option<(Rect, usize)>,
+    pub modes: Vec<(PomodoroMode, Rect)>,
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.left() && column < area.right() && row >= area.top() && row < area.bottom()
+}
+
+impl ClickAreas {
+    pub fn in_timer(&self, column: u16, row: u16) -> bool {
+        self.timer.is_some_and(|timer| contains(timer, column, row))
+    }
+
+    /// Which row of the task list is drawn at the given position, counting rows scrolled past.
+    pub fn task_row_at(&self, column: u16, row: u16) -> Option<usize> {
+        let (area, offset) = self.tasks?;
+
+        contains(area, column, row).then(|| offset + (row - area.y) as usize)
+    }
+
+    pub fn mode_at(&self, column: u16, row: u16) -> Option<PomodoroMode> {
+        self.modes
+            .iter()
+            .find(|(_, area)| contains(*area, column, row))
+            .map(|(mode, _)| *mode)
+    }
+}
+
+/// The index of the first item a list shows, following how tui scrolls a `List` just far enough
+/// to keep the selected item in view. `ListState` doesn't expose its own copy.
+pub fn list_offset(offset: usize, selected: Option<usize>, length: usize, height: usize) -> usize {
+    if length == 0 {
+        return offset;
+    }
+
+    let offset = offset.min(length - 1);
+    let selected = selected.unwrap_or(0).min(length - 1);
+
+    if selected < offset {
+        selected
+    } else if selected >= offset + height {
+        selected + 1 - height
+    } else {
+        offset
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use tui::style::Color;
+
+use crate::{
+    config::ConfigError,
+    models::pomodoro_mode::PomodoroMode,
+    paths::{expand_tilde, xdg_dir},
+};
+
+/// A color written as a name (`light_green`), a hex code (`#a6e3a1`) or a 256-color palette index.
+/// `reset` leaves the terminal's own color in place.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ThemeColor(pub Color);
+
+const NAMED_COLORS: [(&str, Color); 17] = [
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("dark_gray", Color::DarkGray),
+    ("light_red", Color::LightRed),
+    ("light_green", Color::LightGreen),
+    ("light_yellow", Color::LightYellow),
+    ("light_blue", Color::LightBlue),
+    ("light_magenta", Color::LightMagenta),
+    ("light_cyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+impl FromStr for ThemeColor {
+    type Err = String;
+
+    fn from_str(color: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid color `{}`", color);
+
+        if let Some(hex) = color.strip_prefix('#') {
+            let channel = |index: usize| {
+                hex.get(index..index + 2)
+                    .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                    .ok_or_else(invalid)
+            };
+
+            if hex.len() != 6 {
+                return Err(invalid());
+            }
+
+            return Ok(Self(Color::Rgb(channel(0)?, channel(2)?, channel(4)?)));
+        }
+
+        if let Ok(index) = color.parse::<u8>() {
+            return Ok(Self(Color::Indexed(index)));
+        }
+
+        NAMED_COLORS
+            .iter()
+            .find(|(name, _)| *name == color.to_lowercase())
+            .map(|(_, color)| Self(*color))
+            .ok_or_else(invalid)
+    }
+}
+
+impl TryFrom<String> for ThemeColor {
+    type Error = String;
+
+    fn try_from(color: String) -> Result<Self, Self::Error> {
+        color.parse()
+    }
+}
+
+impl From<ThemeColor> for String {
+    fn from(color: ThemeColor) -> Self {
+        match color.0 {
+            Color::Rgb(red, green, blue) => format!("#{:02x}{:02x}{:02x}", red, green, blue),
+            Color::Indexed(index) => index.to_string(),
+            named => NAMED_COLORS
+                .iter()
+                .find(|(_, color)| *color == named)
+                .map_or("reset", |(name, _)| name)
+                .to_string(),
+        }
+    }
+}
+
+/// Colors used throughout the interface.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub border: ThemeColor,
+    pub title: ThemeColor,
+    /// The clock and the message below it.
+    pub timer: ThemeColor,
+    pub overtime: ThemeColor,
+    /// Border of the timer pane in each mode.
+    pub pomodoro: ThemeColor,
+    pub short_break: ThemeColor,
+    pub long_break: ThemeColor,
+    pub flowtime: ThemeColor,
+    pub gauge: ThemeColor,
+    /// The selected task, profile or chart bar.
+    pub highlight: ThemeColor,
+    pub task_open: ThemeColor,
+    pub task_completed: ThemeColor,
+    pub help_border: ThemeColor,
+    pub help_heading: ThemeColor,
+    pub help_key: ThemeColor,
+    pub help_text: ThemeColor,
+    pub error: ThemeColor,
+}
+
+pub const DARK_THEME: &str = "dark";
+pub const LIGHT_THEME: &str = "light";
+
+impl Theme {
+    /// The default theme, for terminals with a dark background.
+    pub fn dark() -> Self {
+        Self {
+            border: ThemeColor(Color::Reset),
+            title: ThemeColor(Color::Reset),
+            timer: ThemeColor(Color::Reset),
+            overtime: ThemeColor(Color::Yellow),
+            pomodoro: ThemeColor(Color::LightRed),
+            short_break: ThemeColor(Color::LightGreen),
+            long_break: ThemeColor(Color::LightBlue),
+            flowtime: ThemeColor(Color::LightMagenta),
+            gauge: ThemeColor(Color::White),
+            highlight: ThemeColor(Color::LightGreen),
+            task_open: ThemeColor(Color::Reset),
+            task_completed: ThemeColor(Color::DarkGray),
+            help_border: ThemeColor(Color::Reset),
+            help_heading: ThemeColor(Color::Reset),
+            help_key: ThemeColor(Color::Reset),
+            help_text: ThemeColor(Color::Reset),
+            error: ThemeColor(Color::Red),
+        }
+    }
+
+    /// A theme for terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            border: ThemeColor(Color::DarkGray),
+            title: ThemeColor(Color::Black),
+            timer: ThemeColor(Color::Black),
+            overtime: ThemeColor(Color::Magenta),
+            pomodoro: ThemeColor(Color::Red),
+            short_break: ThemeColor(Color::Green),
+            long_break: ThemeColor(Color::Blue),
+            flowtime: ThemeColor(Color::Magenta),
+            gauge: ThemeColor(Color::Blue),
+            highlight: ThemeColor(Color::Blue),
+            task_open: ThemeColor(Color::Black),
+            task_completed: ThemeColor(Color::Gray),
+            help_border: ThemeColor(Color::DarkGray),
+            help_heading: ThemeColor(Color::Black),
+            help_key: ThemeColor(Color::Blue),
+            help_text: ThemeColor(Color::Black),
+            error: ThemeColor(Color::Red),
+        }
+    }
+
+    fn built_in(name: &str) -> Option<Self> {
+        match name {
+            DARK_THEME => Some(Self::dark()),
+            LIGHT_THEME => Some(Self::light()),
+            _ => None,
+        }
+    }
+
+    /// Where a theme is looked up: names refer to `$XDG_CONFIG_HOME/pomodoro/themes/<name>.toml`,
+    /// anything that looks like a path is used as is.
+    pub fn path(name: &str) -> PathBuf {
+        if name.contains('/') || name.ends_with(".toml") {
+            expand_tilde(Path::new(name))
+        } else {
+            xdg_dir("XDG_CONFIG_HOME", ".config")
+                .join("pomodoro")
+                .join("themes")
+                .join(format!("{}.toml", name))
+        }
+    }
+
+    /// Load a built-in theme or a theme file. Theme files start from the theme named by their
+    /// `base` key, `dark` unless given, and only need to list the colors they change.
+    pub fn load(name: &str) -> Result<Self, ConfigError> {
+        if let Some(theme) = Self::built_in(name) {
+            return Ok(theme);
+        }
+
+        let path = Self::path(name);
+        let content = fs::read_to_string(&path).map_err(|source| ConfigError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let parse_error = |source| ConfigError::Parse {
+            path: path.clone(),
+            source,
+        };
+
+        let mut colors: toml::Table = toml::from_str(&content).map_err(parse_error)?;
+        let base = match colors.remove("base") {
+            Some(toml::Value::String(base)) => Self::built_in(&base).ok_or_else(|| {
+                ConfigError::Invalid(format!("{}: unknown base theme `{}`", path.display(), base))
+            })?,
+            Some(_) => {
+                return Err(ConfigError::Invalid(format!(
+                    "{}: `base` must be a string",
+                    path.display()
+                )))
+            }
+            None => Self::dark(),
+        };
+
+        let mut theme = toml::Table::try_from(base).expect("themes serialize to a table");
+        theme.extend(colors);
+
+        theme.try_into().map_err(parse_error)
+    }
+
+    /// The color of the timer pane in the given mode.
+    pub fn mode(&self, mode: PomodoroMode) -> Color {
+        match mode {
+            PomodoroMode::Pomodoro => self.pomodoro.0,
+            PomodoroMode::ShortBreak => self.short_break.0,
+            PomodoroMode::LongBreak => self.long_break.0,
+            PomodoroMode::Flowtime => self.flowtime.0,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
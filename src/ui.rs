@@ -1,168 +1,413 @@
 use tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Corner, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Wrap},
+    widgets::{
+        BarChart, Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row,
+        Sparkline, Table, Wrap,
+    },
     Frame,
 };
 
-use figlet_rs::FIGfont;
-
-use crate::{AppState, StudyMode};
+use crate::{
+    font::Font,
+    keymap::{Action, Section},
+    models::{
+        input_mode::{InputField, InputMode, TaskForm},
+        pomodoro_mode::PomodoroMode,
+        stats::{StatsGrouping, STATS_DAYS},
+        task::Task,
+        timer::Timer,
+    },
+    screen::{self, Arrangement, ClickAreas},
+    theme::Theme,
+    AppState, StudyMode,
+};
 
 pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
     let size = f.size();
+    let theme = &app_state.theme;
+    let mut click_areas = ClickAreas::default();
 
     let create_block = |title: &str| {
         Block::default()
             .borders(Borders::ALL)
-            .title(title.to_string())
+            .border_style(Style::default().fg(theme.border.0))
+            .title(Span::styled(
+                title.to_string(),
+                Style::default().fg(theme.title.0),
+            ))
+    };
+
+    // The timer pane takes the color of the current mode
+    let create_timer_block = |title: &str| {
+        create_block(title)
+            .border_style(Style::default().fg(theme.mode(app_state.timer.pomodoro_mode)))
     };
 
     let create_gauge = || {
-        Gauge::default()
+        let gauge = Gauge::default()
             .block(create_block(""))
-            .gauge_style(Style::default().fg(Color::White))
-            .percent(app_state.timer.percentage)
+            .gauge_style(Style::default().fg(theme.gauge.0));
+
+        if app_state.timer.is_count_up() {
+            // With no end to count down to, show the break earned so far instead
+            let earned_break = app_state.flowtime.break_for(app_state.timer.elapsed());
+            let max_break = app_state.flowtime.max_break * 60;
+
+            gauge
+                .percent((earned_break.as_secs() * 100 / max_break.max(1)).min(100) as u16)
+                .label(format!(
+                    "Break earned: {}:{:02}",
+                    earned_break.as_secs() / 60,
+                    earned_break.as_secs() % 60
+                ))
+        } else {
+            gauge.percent(app_state.timer.percentage())
+        }
     };
 
-    let create_timer = || {
-        let mut timer_text = render_ascii_text(
-            // 60 * 60 = 60 minutes in seconds
-            if app_state.timer.time_remaining.as_secs() >= (60 * 60) {
-                app_state.timer.hh_mm_ss()
-            } else {
-                app_state.timer.mm_ss()
+    let create_timer = |area: Rect| {
+        let (clock, mut message, style) = if app_state.timer.overtime_notified {
+            (
+                app_state.timer.overtime_mm_ss(),
+                format!(
+                    "Overtime! Press {} to start the next timer",
+                    app_state.keymap.label(Action::NextTimer)
+                ),
+                Style::default().fg(theme.overtime.0),
+            )
+        } else {
+            (
+                // 60 * 60 = 60 minutes in seconds
+                if app_state.timer.display_secs() >= (60 * 60) {
+                    app_state.timer.hh_mm_ss()
+                } else {
+                    app_state.timer.mm_ss()
+                },
+                String::from("Keep it up, you got this!"),
+                Style::default().fg(theme.timer.0),
+            )
+        };
+
+        if let Some(active) = app_state.active_task {
+            message.push_str(&format!(
+                "\nWorking on: {}",
+                app_state.tasks.items[active].title
+            ));
+        }
+
+        // Leave room for the message below the clock
+        let clock_area = Rect {
+            height: area.height.saturating_sub(message.lines().count() as u16),
+            ..area
+        };
+        let mut timer_text = render_ascii_text(&clock, &app_state.fonts, clock_area);
+        timer_text.push_str(&message);
+
+        // Center the clock vertically in whatever space it's given
+        let padding = area
+            .height
+            .saturating_sub(timer_text.lines().count() as u16)
+            / 2;
+        timer_text.insert_str(0, &"\n".repeat(padding as usize));
+
+        Paragraph::new(timer_text)
+            .alignment(Alignment::Center)
+            .style(style)
+    };
+
+    // A single line in place of the timer pane when there isn't room for the clock
+    let create_compact_timer = || {
+        let (clock, style) = if app_state.timer.overtime_notified {
+            (
+                app_state.timer.overtime_mm_ss(),
+                Style::default().fg(theme.overtime.0),
+            )
+        } else {
+            (
+                app_state.timer.mm_ss(),
+                Style::default().fg(theme.mode(app_state.timer.pomodoro_mode)),
+            )
+        };
+
+        let mut line = vec![
+            Span::styled(
+                format!("{} {}", mode_title(app_state), clock),
+                style.add_modifier(Modifier::BOLD),
+            ),
+            Span::from(format!(" - {}", help_hint(app_state))),
+        ];
+        if let Some(active) = app_state.active_task {
+            line.push(Span::from(format!(
+                " - {}",
+                app_state.tasks.items[active].title
+            )));
+        }
+
+        Paragraph::new(Spans::from(line))
+    };
+
+    // Labels for switching modes with the mouse, and where each one is drawn
+    let create_mode_labels = |area: Rect| {
+        let mut labels = vec![];
+        let mut areas = vec![];
+        let mut x = area.x;
+
+        for mode in PomodoroMode::ALL {
+            let label = mode.to_string();
+            let width = label.len() as u16;
+            if x + width > area.right() {
+                break;
             }
-            .as_str(),
-        );
-        timer_text.push_str("Keep it up, you got this!");
 
-        Paragraph::new(timer_text).alignment(Alignment::Center)
+            if !labels.is_empty() {
+                labels.push(Span::styled(" │ ", Style::default().fg(theme.border.0)));
+            }
+            labels.push(Span::styled(
+                label,
+                if mode == app_state.timer.pomodoro_mode {
+                    Style::default()
+                        .fg(theme.mode(mode))
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                },
+            ));
+            areas.push((mode, Rect { x, width, ..area }));
+            x += width + 3;
+        }
+
+        // What's left of the row after the labels and a gap
+        let rest = Rect {
+            x: x.min(area.right()),
+            width: area.right().saturating_sub(x),
+            ..area
+        };
+
+        (Paragraph::new(Spans::from(labels)), areas, rest)
     };
 
-    let create_watermark = |text: &str| {
+    // Left out rather than cut short when there isn't room for all of it
+    let create_watermark = |text: &str, area: Rect| {
+        let text = if text.chars().count() <= area.width as usize {
+            text
+        } else {
+            ""
+        };
+
         Paragraph::new(text.to_string())
             .alignment(Alignment::Right)
             .style(Style::default().add_modifier(Modifier::ITALIC))
     };
 
+    // The mode labels, when the mouse is on, take the start of the watermark's row
+    let render_watermark_row = |f: &mut Frame<B>, click_areas: &mut ClickAreas, area: Rect| {
+        let mut watermark_area = area;
+        if app_state.mouse {
+            let (labels, label_areas, rest) = create_mode_labels(area);
+            f.render_widget(labels, area);
+            click_areas.modes = label_areas;
+            watermark_area = rest;
+        }
+        f.render_widget(
+            create_watermark("Made by Chooky <3", watermark_area),
+            watermark_area,
+        );
+    };
+
     let create_control_text = |control: &str, action: &str| {
         vec![
             Span::styled(
-                format!("{}: ", control.to_string()),
-                Style::default().add_modifier(Modifier::BOLD),
+                format!("{}: ", control),
+                Style::default()
+                    .fg(theme.help_key.0)
+                    .add_modifier(Modifier::BOLD),
             ),
-            Span::from(action.to_string()),
+            Span::styled(action.to_string(), Style::default().fg(theme.help_text.0)),
         ]
     };
 
     // Todo: Clean this up, possible a Help struct
-    let controls = Paragraph::new(vec![
-        Spans::from(Span::styled(
-            "Timer:",
-            Style::default().add_modifier(Modifier::UNDERLINED),
-        )),
-        Spans::from(create_control_text("p", "Pomodoro timer")),
-        Spans::from(create_control_text("s", "Short break timer")),
-        Spans::from(create_control_text("l", "Long break timer")),
-        Spans::from(create_control_text("Space", "Pause/Unpause timer")),
-        Spans::from(create_control_text("r", "Reset timer")),
-        Spans::from(""),
-        Spans::from(Span::styled(
-            "Tasks:",
-            Style::default().add_modifier(Modifier::UNDERLINED),
-        )),
-        Spans::from(create_control_text("j/k", "Scroll task list")),
-        Spans::from(create_control_text("S", "Save tasks")),
-        Spans::from(create_control_text("Enter", "Mark/Unmark task as complete")),
-        Spans::from(create_control_text(
-            "+/-",
-            "Increase/Decrease pomodoros taken for task",
-        )),
-        Spans::from(""),
-        Spans::from(Span::styled(
-            "Misc:",
-            Style::default().add_modifier(Modifier::UNDERLINED),
-        )),
-        Spans::from(create_control_text("q", "Quit application")),
-    ])
-    .wrap(Wrap { trim: false })
-    .block(create_block("Controls"));
+    // Generated from the keymap so remapped keys always show up correctly
+    let mut controls_text = vec![];
+    for section in Section::ALL {
+        if !controls_text.is_empty() {
+            controls_text.push(Spans::from(""));
+        }
 
-    match app_state.study_mode {
-        StudyMode::Normal => {
-            let top = Layout::default()
-                .margin(1)
-                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
-                .split(size);
+        controls_text.push(Spans::from(Span::styled(
+            section.title(),
+            Style::default()
+                .fg(theme.help_heading.0)
+                .add_modifier(Modifier::UNDERLINED),
+        )));
 
-            // Todo: Create as_str() implementation for pomodoro_mode
-            f.render_widget(
-                create_block(
-                    format!(
-                        "{} - Press ? for help",
-                        app_state.timer.pomodoro_mode.to_string()
-                    )
-                    .as_str(),
-                ),
-                top[0],
-            );
+        for action in Action::ALL
+            .into_iter()
+            .filter(|action| action.section() == section)
+        {
+            let keys = app_state.keymap.label(action);
+            if !keys.is_empty() {
+                controls_text.push(Spans::from(create_control_text(
+                    &keys,
+                    action.description(),
+                )));
+            }
+        }
+    }
 
-            let inner_top = Layout::default()
-                .margin(1)
-                .constraints([
-                    Constraint::Percentage(35),
-                    Constraint::Percentage(40),
-                    Constraint::Percentage(35),
-                ])
-                .split(top[0]);
+    // Sized to fit the controls where possible, scrolling through them where not
+    let controls_width = controls_text
+        .iter()
+        .map(|spans| spans.width())
+        .max()
+        .unwrap_or_default() as u16;
+    let controls_area = screen::popup(size, controls_width, controls_text.len() as u16);
+    let controls_lines: u16 = controls_text
+        .iter()
+        .map(|spans| {
+            let inner_width = controls_area.width.saturating_sub(2).max(1) as usize;
+            spans.width().max(1).div_ceil(inner_width) as u16
+        })
+        .sum();
+    let max_scroll = controls_lines.saturating_sub(controls_area.height.saturating_sub(2));
+    let help_scroll = app_state.help_scroll.min(max_scroll);
 
-            let watermark = create_watermark("Made by Chooky <3");
-            f.render_widget(watermark, inner_top[0]);
+    let controls_title = if max_scroll > 0 {
+        format!(
+            "Controls - {} / {} to scroll",
+            app_state.keymap.label(Action::NextTask),
+            app_state.keymap.label(Action::PreviousTask)
+        )
+    } else {
+        String::from("Controls")
+    };
+    let controls = Paragraph::new(controls_text)
+        .wrap(Wrap { trim: false })
+        .scroll((help_scroll, 0))
+        .block(
+            create_block(&controls_title).border_style(Style::default().fg(theme.help_border.0)),
+        );
 
-            let timer = create_timer();
-            f.render_widget(timer, inner_top[1]);
+    let timer_title = format!("{} - {}", mode_title(app_state), help_hint(app_state));
 
-            let bottom = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(100)])
-                .split(top[1]);
+    match app_state.study_mode {
+        StudyMode::Normal => {
+            let areas = screen::normal(size);
+
+            if areas.arrangement == Arrangement::Compact {
+                f.render_widget(create_compact_timer(), areas.timer);
+            } else {
+                f.render_widget(create_timer_block(&timer_title), areas.timer);
+
+                let pane = screen::timer_pane(areas.timer, 0);
+                render_watermark_row(f, &mut click_areas, pane.watermark);
+                f.render_widget(create_timer(pane.clock), pane.clock);
+            }
+            click_areas.timer = Some(areas.timer);
 
-            let tasks: Vec<ListItem> = app_state
-                .tasks
-                .items
+            // Subtasks are indented under their parent, which shows whether they're collapsed
+            let visible_tasks = app_state.tasks.visible();
+            let tasks: Vec<ListItem> = visible_tasks
                 .iter()
-                .map(|task| ListItem::new(task.list_print()))
+                .map(|&index| {
+                    let task = &app_state.tasks.items[index];
+                    let marker = match (app_state.tasks.has_children(index), task.collapsed) {
+                        (false, _) => "  ",
+                        (true, false) => "▾ ",
+                        (true, true) => "▸ ",
+                    };
+                    let text = format!(
+                        "{}{}{}",
+                        "  ".repeat(task.depth),
+                        marker,
+                        task.list_print(Task::pomodoros(&app_state.tasks.items, index))
+                    );
+
+                    let item = ListItem::new(text).style(Style::default().fg(if task.completed {
+                        theme.task_completed.0
+                    } else {
+                        theme.task_open.0
+                    }));
+
+                    // The task pomodoros are credited to
+                    if app_state.active_task == Some(index) {
+                        item.style(Style::default().add_modifier(Modifier::BOLD))
+                    } else {
+                        item
+                    }
+                })
                 .collect();
 
             let tasks_list = List::new(tasks)
-                .block(create_block("Tasks"))
-                .highlight_style(Style::default().fg(Color::LightGreen))
+                .highlight_style(Style::default().fg(theme.highlight.0))
                 .start_corner(Corner::TopLeft);
 
-            f.render_stateful_widget(tasks_list, bottom[0], &mut app_state.tasks.state);
+            let filtering = matches!(app_state.input_mode, InputMode::FilteringTasks);
+            let pane = screen::task_pane(areas.tasks, filtering || app_state.tasks.is_filtered());
+            app_state.tasks_offset = screen::list_offset(
+                app_state.tasks_offset,
+                app_state.tasks.state.selected(),
+                visible_tasks.len(),
+                pane.rows.height as usize,
+            );
+            click_areas.tasks = Some((pane.rows, app_state.tasks_offset));
+
+            f.render_widget(
+                create_block(if app_state.tasks_dirty {
+                    "Tasks (unsaved)"
+                } else {
+                    "Tasks"
+                }),
+                areas.tasks,
+            );
+            f.render_stateful_widget(tasks_list, pane.rows, &mut app_state.tasks.state);
 
-            if app_state.show_help_menu {
-                // Todo: conditionally render the size of this popup
-                let area = centered_rect(60, 70, size);
-                f.render_widget(Clear, area); //this clears out the background
-                f.render_widget(controls, area);
+            if let Some(filter_area) = pane.filter {
+                let filter = Paragraph::new(format!("/{}", app_state.task_filter))
+                    .style(Style::default().fg(theme.highlight.0));
+                f.render_widget(filter, filter_area);
+
+                if filtering {
+                    f.set_cursor(
+                        (filter_area.x + 1 + app_state.task_filter.chars().count() as u16)
+                            .min(filter_area.right().saturating_sub(1)),
+                        filter_area.y,
+                    );
+                }
             }
         }
         StudyMode::Zen => {
+            let areas = screen::zen(size);
+
+            if areas.arrangement == Arrangement::Compact {
+                f.render_widget(create_compact_timer(), areas.timer);
+                f.render_widget(create_gauge().block(Block::default()), areas.gauge);
+            } else {
+                f.render_widget(create_timer_block(&timer_title), areas.timer);
+
+                let pane = screen::timer_pane(areas.timer, screen::GAUGE_HEIGHT);
+                render_watermark_row(f, &mut click_areas, pane.watermark);
+                f.render_widget(create_timer(pane.clock), pane.clock);
+                f.render_widget(create_gauge(), areas.gauge);
+            }
+            click_areas.timer = Some(areas.timer);
+        }
+        StudyMode::Stats => {
             let top = Layout::default()
                 .margin(1)
                 .constraints([Constraint::Percentage(100)])
                 .split(size);
 
-            // Todo: Create as_str() implementation for pomodoro_mode
             f.render_widget(
                 create_block(
                     format!(
-                        "{} - Press ? for help",
-                        app_state.timer.pomodoro_mode.to_string()
+                        "Statistics - {} {} - {}",
+                        app_state.timer.pomodoro_mode,
+                        app_state.timer.mm_ss(),
+                        help_hint(app_state)
                     )
                     .as_str(),
                 ),
@@ -172,41 +417,422 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
             let inner_top = Layout::default()
                 .margin(1)
                 .constraints([
-                    Constraint::Percentage(20),
-                    Constraint::Percentage(60),
-                    Constraint::Percentage(5),
+                    Constraint::Percentage(40),
+                    Constraint::Length(5),
+                    Constraint::Length(1),
+                    Constraint::Min(3),
                 ])
                 .split(top[0]);
 
-            let gauge = create_gauge();
-            f.render_widget(gauge, inner_top[2]);
+            let stats = &app_state.stats;
+
+            let day_labels: Vec<String> = stats
+                .days
+                .iter()
+                .map(|day| day.date.format("%d").to_string())
+                .collect();
+            let pomodoros_per_day: Vec<(&str, u64)> = stats
+                .days
+                .iter()
+                .zip(day_labels.iter())
+                .map(|(day, label)| (label.as_str(), day.pomodoros))
+                .collect();
+
+            let bar_chart = BarChart::default()
+                .block(create_block(
+                    format!("Pomodoros per day (last {} days)", STATS_DAYS).as_str(),
+                ))
+                .data(&pomodoros_per_day)
+                .bar_width(3)
+                .bar_gap(1)
+                .bar_style(Style::default().fg(theme.highlight.0))
+                .value_style(
+                    Style::default()
+                        .fg(theme.highlight.0)
+                        .add_modifier(Modifier::REVERSED),
+                );
+            f.render_widget(bar_chart, inner_top[0]);
+
+            let focus_minutes: Vec<u64> = stats.days.iter().map(|day| day.focus_minutes).collect();
+            let sparkline = Sparkline::default()
+                .block(create_block(
+                    format!(
+                        "Focus minutes - {} total",
+                        focus_minutes.iter().sum::<u64>()
+                    )
+                    .as_str(),
+                ))
+                .data(&focus_minutes)
+                .style(Style::default().fg(theme.highlight.0));
+            f.render_widget(sparkline, inner_top[1]);
+
+            let streak = Paragraph::new(Spans::from(vec![
+                Span::styled(
+                    "Current streak: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::from(format!(
+                    "{} day{}",
+                    stats.current_streak,
+                    if stats.current_streak == 1 { "" } else { "s" }
+                )),
+            ]));
+            f.render_widget(streak, inner_top[2]);
+
+            let group_key = app_state.keymap.label(Action::GroupStats);
+            let table = match app_state.stats_grouping {
+                StatsGrouping::Task => {
+                    let task_rows = stats.tasks.iter().map(|task| {
+                        Row::new(vec![
+                            Cell::from(task.title.as_str()),
+                            Cell::from(task.pomodoros_expected.to_string()),
+                            Cell::from(task.pomodoros_completed.to_string()),
+                            Cell::from(task.pomodoros_logged.to_string()),
+                        ])
+                    });
+
+                    Table::new(task_rows)
+                        .header(
+                            Row::new(vec!["Task", "Expected", "Completed", "Logged"])
+                                .style(Style::default().add_modifier(Modifier::UNDERLINED)),
+                        )
+                        .block(create_block(
+                            format!("Estimate vs actual - {} to group", group_key).as_str(),
+                        ))
+                        .widths(&[
+                            Constraint::Percentage(55),
+                            Constraint::Percentage(15),
+                            Constraint::Percentage(15),
+                            Constraint::Percentage(15),
+                        ])
+                }
+                StatsGrouping::Project | StatsGrouping::Tag => {
+                    let (groups, heading, ungrouped) =
+                        if app_state.stats_grouping == StatsGrouping::Project {
+                            (&stats.projects, "Project", "No project")
+                        } else {
+                            (&stats.tags, "Tag", "Untagged")
+                        };
+                    let group_rows = groups.iter().map(|group| {
+                        Row::new(vec![
+                            Cell::from(group.name.as_deref().unwrap_or(ungrouped)),
+                            Cell::from(group.pomodoros_expected.to_string()),
+                            Cell::from(group.pomodoros_completed.to_string()),
+                            Cell::from(group.pomodoros_logged.to_string()),
+                            Cell::from(group.focus_minutes.to_string()),
+                        ])
+                    });
+
+                    Table::new(group_rows)
+                        .header(
+                            Row::new(vec![
+                                heading,
+                                "Expected",
+                                "Completed",
+                                "Logged",
+                                "Focus minutes",
+                            ])
+                            .style(Style::default().add_modifier(Modifier::UNDERLINED)),
+                        )
+                        .block(create_block(
+                            format!("By {} - {} to group", heading.to_lowercase(), group_key)
+                                .as_str(),
+                        ))
+                        .widths(&[
+                            Constraint::Percentage(40),
+                            Constraint::Percentage(15),
+                            Constraint::Percentage(15),
+                            Constraint::Percentage(15),
+                            Constraint::Percentage(15),
+                        ])
+                }
+            };
+            f.render_widget(table, inner_top[3]);
+        }
+    }
+
+    if app_state.show_help_menu {
+        f.render_widget(Clear, controls_area); //this clears out the background
+        f.render_widget(controls, controls_area);
+    }
+    app_state.help_scroll = help_scroll;
+    app_state.click_areas = click_areas;
+
+    match &app_state.input_mode {
+        InputMode::Normal => {}
+        // Drawn in the task list
+        InputMode::FilteringTasks => {}
+        InputMode::AddingTask(form) => render_task_form(f, "New task", form, theme, size),
+        InputMode::AddingSubtask(parent, form) => render_task_form(
+            f,
+            &format!(
+                "New subtask of \"{}\"",
+                app_state.tasks.items[*parent].title
+            ),
+            form,
+            theme,
+            size,
+        ),
+        InputMode::EditingTask(_, form) => render_task_form(f, "Edit task", form, theme, size),
+        InputMode::ConfirmDelete(index) => {
+            let area = centered_rect(50, 20, size);
+            let confirmation = Paragraph::new(vec![
+                Spans::from(match app_state.tasks.subtree(*index).len() - 1 {
+                    0 => format!("Delete \"{}\"?", app_state.tasks.items[*index].title),
+                    subtasks => format!(
+                        "Delete \"{}\" and its {} subtask{}?",
+                        app_state.tasks.items[*index].title,
+                        subtasks,
+                        if subtasks == 1 { "" } else { "s" }
+                    ),
+                }),
+                Spans::from(""),
+                Spans::from(vec![
+                    Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::from(": Yes  "),
+                    Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::from(": No"),
+                ]),
+            ])
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false })
+            .block(create_block("Delete task"));
+
+            f.render_widget(Clear, area);
+            f.render_widget(confirmation, area);
+        }
+        InputMode::PickingProfile(index) => {
+            let area = centered_rect(40, 40, size);
+            let profiles: Vec<ListItem> = app_state
+                .profiles
+                .iter()
+                .map(|profile| {
+                    let periods: Vec<String> = profile
+                        .periods
+                        .iter()
+                        .map(|period| match period.mode {
+                            PomodoroMode::Flowtime => String::from("flow"),
+                            _ if period.duration == 0 => String::from("earned"),
+                            _ => period.duration.to_string(),
+                        })
+                        .collect();
+
+                    ListItem::new(format!("{} ({})", profile.name, periods.join("/")))
+                })
+                .collect();
+
+            let profiles_list = List::new(profiles)
+                .block(create_block("Profiles - Enter to select, Esc to cancel"))
+                .highlight_style(Style::default().fg(theme.highlight.0));
+
+            let mut state = ListState::default();
+            state.select(Some(*index));
 
-            let watermark = create_watermark("Made by Chooky <3");
-            f.render_widget(watermark, inner_top[0]);
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(profiles_list, area, &mut state);
+        }
+        InputMode::ConfirmReload => {
+            let area = centered_rect(50, 20, size);
+            let confirmation = Paragraph::new(vec![
+                Spans::from(
+                    "The task file changed on disk, but the task list has unsaved changes.",
+                ),
+                Spans::from(""),
+                Spans::from(vec![
+                    Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::from(": Reload the file  "),
+                    Span::styled("k", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::from(": Keep my changes"),
+                ]),
+            ])
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false })
+            .block(create_block("Task file changed"));
 
-            let timer = create_timer();
-            f.render_widget(timer, inner_top[1]);
+            f.render_widget(Clear, area);
+            f.render_widget(confirmation, area);
+        }
+        InputMode::ConfirmQuit => {
+            let area = centered_rect(50, 20, size);
+            let confirmation = Paragraph::new(vec![
+                Spans::from("The task list has unsaved changes."),
+                Spans::from(""),
+                Spans::from(vec![
+                    Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::from(": Save and quit  "),
+                    Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::from(": Quit without saving  "),
+                    Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::from(": Cancel"),
+                ]),
+            ])
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false })
+            .block(create_block("Quit"));
+
+            f.render_widget(Clear, area);
+            f.render_widget(confirmation, area);
+        }
+        InputMode::ConfirmResume(saved) => {
+            let area = centered_rect(50, 25, size);
+            let timer = Timer::restore(&saved.timer, saved.time_away());
+            let clock = if timer.overtime_notified {
+                timer.overtime_mm_ss()
+            } else {
+                timer.mm_ss()
+            };
 
-            // Todo: Code duplication - find a way to move this logic
-            if app_state.show_help_menu {
-                // Todo: conditionally render the size of this popup
-                let area = centered_rect(60, 70, size);
-                f.render_widget(Clear, area); //this clears out the background
-                f.render_widget(controls, area);
+            let mut text = vec![Spans::from(format!(
+                "Resume {} at {}?",
+                timer.pomodoro_mode, clock
+            ))];
+            if let Some(task) = &saved.task {
+                text.push(Spans::from(format!("Task: {}", task)));
             }
+            text.push(Spans::from(""));
+            text.push(Spans::from(vec![
+                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                Span::from(": Resume  "),
+                Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+                Span::from(": Start over"),
+            ]));
+
+            let confirmation = Paragraph::new(text)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: false })
+                .block(create_block("Resume timer"));
+
+            f.render_widget(Clear, area);
+            f.render_widget(confirmation, area);
         }
     }
+
+    if let Some(error_message) = &app_state.error_message {
+        let area = centered_rect(60, 25, size);
+        let error = Paragraph::new(vec![
+            Spans::from(error_message.as_str()),
+            Spans::from(""),
+            Spans::from(Span::styled(
+                "Press any key to continue",
+                Style::default().add_modifier(Modifier::ITALIC),
+            )),
+        ])
+        .wrap(Wrap { trim: false })
+        .block(create_block("Error").border_style(Style::default().fg(theme.error.0)));
+
+        f.render_widget(Clear, area);
+        f.render_widget(error, area);
+    }
 }
 
-fn render_ascii_text(text: &str) -> String {
-    let standard_font = FIGfont::standard().unwrap();
+/// Draw the text-entry popup used to add and edit tasks.
+fn render_task_form<B: Backend>(
+    f: &mut Frame<B>,
+    title: &str,
+    form: &TaskForm,
+    theme: &Theme,
+    size: Rect,
+) {
+    let field_style = |field: InputField| {
+        if form.field == field {
+            Style::default().fg(theme.highlight.0)
+        } else {
+            Style::default()
+        }
+    };
+
+    let fields = [
+        ("Title: ", form.title.as_str(), InputField::Title),
+        (
+            "Pomodoros expected: ",
+            form.pomodoros_expected.as_str(),
+            InputField::PomodorosExpected,
+        ),
+        ("Project: ", form.project.as_str(), InputField::Project),
+        ("Tags: ", form.tags.as_str(), InputField::Tags),
+    ];
+
+    let mut lines: Vec<Spans> = fields
+        .iter()
+        .map(|&(label, value, field)| {
+            Spans::from(vec![
+                Span::styled(label, Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(value, field_style(field)),
+            ])
+        })
+        .collect();
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "Tab: Switch field  Enter: Save  Esc: Cancel",
+        Style::default().add_modifier(Modifier::ITALIC),
+    )));
 
-    match standard_font.convert(text) {
-        Some(val) => val.to_string(),
-        None => String::new(),
+    let area = screen::popup(size, size.width * 3 / 5, lines.len() as u16);
+    let content = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border.0))
+            .title(Span::styled(
+                title.to_string(),
+                Style::default().fg(theme.title.0),
+            )),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(content, area);
+
+    if let Some((row, (label, value, _))) = fields
+        .iter()
+        .enumerate()
+        .find(|(_, (_, _, field))| *field == form.field)
+    {
+        let column = label.len() + value.chars().count();
+
+        f.set_cursor(
+            (area.x + 1 + column as u16).min(area.right().saturating_sub(2)),
+            area.y + 1 + row as u16,
+        );
+    }
+}
+
+/// Tells the user how to open the help menu, or shows the keys of a chord being typed.
+fn help_hint(app_state: &AppState) -> String {
+    match app_state.keymap.pending() {
+        Some(pending) => format!("{} ...", pending),
+        None => format!(
+            "Press {} for help",
+            app_state.keymap.label(Action::ToggleHelp)
+        ),
     }
 }
 
+/// The current period and its place in the profile. One-off timers just show their mode.
+fn mode_title(app_state: &AppState) -> String {
+    if !app_state.timer_in_cycle() {
+        return app_state.timer.pomodoro_mode.to_string();
+    }
+
+    match app_state.cycle.progress() {
+        Some(progress) => format!("{} {}", app_state.cycle.title(), progress),
+        None => app_state.cycle.title(),
+    }
+}
+
+/// Draw `text` in the first of `fonts` it fits `area` in, falling back to plain text if it's too
+/// big for all of them rather than letting it wrap.
+fn render_ascii_text(text: &str, fonts: &[Font], area: Rect) -> String {
+    fonts
+        .iter()
+        .filter_map(|font| font.render(text))
+        .find(|rendered| {
+            let width = rendered.lines().map(|line| line.chars().count()).max();
+
+            width.unwrap_or(0) <= area.width as usize
+                && rendered.lines().count() <= area.height as usize
+        })
+        .unwrap_or_else(|| format!("{}\n", text))
+}
+
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
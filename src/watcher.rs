@@ -0,0 +1,43 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Notices when a file is changed by another program by polling its modification time, which is
+/// cheap enough to do on every tick.
+pub struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl FileWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+
+        Self { path, modified }
+    }
+
+    /// Whether the file has been written since it was last checked. A file that has gone
+    /// missing isn't counted as a change.
+    pub fn changed(&mut self) -> bool {
+        match modified_time(&self.path) {
+            Some(modified) if Some(modified) != self.modified => {
+                self.modified = Some(modified);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Treat the file as it is now as already seen, e.g. after writing it ourselves.
+    pub fn mark_seen(&mut self) {
+        self.modified = modified_time(&self.path);
+    }
+}